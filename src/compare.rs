@@ -0,0 +1,92 @@
+use crate::{json::Json, EvalError, Value, Variables};
+use num::BigRational;
+use rand::Rng;
+use std::{cmp::Ordering, fmt, num::NonZeroUsize};
+
+/// Estimate how two expressions which may refer to `variables` fare against each other by evaluating both `samples` times.
+///
+/// Every evaluation draws from fresh decks.
+pub fn compare(
+    left: &str,
+    right: &str,
+    variables: &Variables,
+    samples: NonZeroUsize,
+    rng: &mut impl Rng,
+) -> Result<Comparison, EvalError> {
+    let mut greater = 0;
+    let mut equal = 0;
+    let mut lesser = 0;
+    let mut margin = Value::zero();
+    for _ in 0..samples.get() {
+        let left = crate::evaluate_with(left, variables, rng)?.value;
+        let right = crate::evaluate_with(right, variables, rng)?.value;
+        match left.as_number()?.cmp(right.as_number()?) {
+            Ordering::Greater => greater += 1,
+            Ordering::Equal => equal += 1,
+            Ordering::Less => lesser += 1,
+        }
+        margin = margin.add(left.sub(right)?)?;
+    }
+    let margin = margin.div(Value::from(samples.get()))?.into_number()?;
+    Ok(Comparison {
+        samples,
+        greater,
+        equal,
+        lesser,
+        margin,
+    })
+}
+
+#[derive(Debug)]
+pub struct Comparison {
+    samples: NonZeroUsize,
+    greater: usize,
+    equal: usize,
+    lesser: usize,
+    margin: BigRational,
+}
+
+impl Comparison {
+    /// Probability that the left expression is greater than the right expression.
+    pub fn greater(&self) -> f64 {
+        self.ratio(self.greater)
+    }
+
+    /// Probability that both expressions are equal.
+    pub fn equal(&self) -> f64 {
+        self.ratio(self.equal)
+    }
+
+    /// Probability that the left expression is lesser than the right expression.
+    pub fn lesser(&self) -> f64 {
+        self.ratio(self.lesser)
+    }
+
+    /// Mean of the left expression minus the right expression.
+    pub fn margin(&self) -> f64 {
+        crate::value::to_f64(&self.margin)
+    }
+
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("samples", Json::from(self.samples.get())),
+            ("greater", Json::from(self.greater())),
+            ("equal", Json::from(self.equal())),
+            ("lesser", Json::from(self.lesser())),
+            ("margin", Json::from(self.margin())),
+        ])
+    }
+
+    fn ratio(&self, count: usize) -> f64 {
+        count as f64 / self.samples.get() as f64
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "P(A > B) = {}", self.greater())?;
+        writeln!(f, "P(A = B) = {}", self.equal())?;
+        writeln!(f, "P(A < B) = {}", self.lesser())?;
+        write!(f, "E[A - B] = {}", self.margin())
+    }
+}
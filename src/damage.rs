@@ -0,0 +1,50 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use crate::value::Damage;
+use num::BigRational;
+use rand::Rng;
+
+/// Parse any `[{type}]` following a value, making the value damage of that type.
+pub fn label(state: &mut State<impl Rng>, value: Value) -> PartialResult {
+    let Some(label) = state.lexer.matching(Token::Label) else {
+        return Ok(value);
+    };
+    state.output.push_str(label);
+    let ty = label.trim_start_matches('[').trim_end_matches(']');
+    Ok(Value::Damage(Damage::new(ty, value.into_number()?)))
+}
+
+/// Parse the arguments of `resist`, `vulnerable` or `immune`, such as `resist(1d8[slashing] + 2d6[fire], [fire])`, and apply it.
+///
+/// Resistance halves the damage of the given types rounding down, vulnerability doubles it and immunity ignores it.
+pub fn modify(state: &mut State<impl Rng>, name: &str) -> PartialResult {
+    let value = super::expression(state)?;
+    let mut types = Vec::new();
+    while state.lexer.matches(Token::Comma) {
+        let label = state
+            .lexer
+            .matching(Token::Label)
+            .ok_or(EvalError::UnexpectedToken)?;
+        state.output.push_str(", ");
+        state.output.push_str(label);
+        types.push(label.trim_start_matches('[').trim_end_matches(']'));
+    }
+    if state.skipping {
+        return Ok(value);
+    }
+    let damage = match value.unlabeled() {
+        Value::Damage(damage) => damage,
+        // plain numbers have no types to modify
+        value => return Ok(value),
+    };
+    let two = BigRational::from_integer(2.into());
+    Ok(Value::Damage(damage.scale(|ty, amount| {
+        if !types.contains(&ty) {
+            return amount;
+        }
+        match name {
+            "resist" => (amount / &two).floor(),
+            "vulnerable" => amount * &two,
+            _ => BigRational::from_integer(0.into()),
+        }
+    })))
+}
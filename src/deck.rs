@@ -0,0 +1,431 @@
+use super::{value::NaturalError, EvalError, PartialResult, State, Token, Value};
+use num::{BigInt, ToPrimitive as _};
+use rand::{seq::SliceRandom as _, Rng};
+use std::{collections::BTreeMap, fmt};
+
+const SUITS: [&str; 4] = ["clubs", "diamonds", "hearts", "spades"];
+
+const RANKS: [&str; 13] = [
+    "2", "3", "4", "5", "6", "7", "8", "9", "10", "jack", "queen", "king", "ace",
+];
+
+const MAJOR_ARCANA: [&str; 22] = [
+    "the fool",
+    "the magician",
+    "the high priestess",
+    "the empress",
+    "the emperor",
+    "the hierophant",
+    "the lovers",
+    "the chariot",
+    "strength",
+    "the hermit",
+    "wheel of fortune",
+    "justice",
+    "the hanged man",
+    "death",
+    "temperance",
+    "the devil",
+    "the tower",
+    "the star",
+    "the moon",
+    "the sun",
+    "judgement",
+    "the world",
+];
+
+const MINOR_SUITS: [&str; 4] = ["wands", "cups", "swords", "pentacles"];
+
+const MINOR_RANKS: [&str; 14] = [
+    "ace", "2", "3", "4", "5", "6", "7", "8", "9", "10", "page", "knight", "queen", "king",
+];
+
+/// Parse the arguments of `draw`, `discard`, `reshuffle` or `shuffle`, such as `draw(standard, 2)`, and apply it to the named deck.
+///
+/// Drawing a single card produces its value labeled with its name, and drawing a count of cards produces a list of them.
+/// The other functions produce the number of cards left to draw.
+pub fn main(state: &mut State<impl Rng>, function: &str) -> PartialResult {
+    let name = state
+        .lexer
+        .matching(Token::Identifier)
+        .ok_or(EvalError::UnexpectedToken)?;
+    state.output.push_str(name);
+    let count = if function == "draw" && state.lexer.matches(Token::Comma) {
+        state.output.push_str(", ");
+        Some(super::expression(state)?)
+    } else {
+        None
+    };
+    if state.skipping {
+        return Ok(match count {
+            Some(count) => {
+                count.as_number()?;
+                Value::from_iter([Value::zero()])
+            }
+            None => Value::zero(),
+        });
+    }
+    let deck = state.decks.get_or_insert(name, &mut *state.rng)?;
+    match function {
+        "draw" => {
+            let cards = match &count {
+                Some(count) => match count.to_natural() {
+                    Ok(cards) => cards.to_usize().unwrap_or(usize::MAX),
+                    Err(NaturalError::Type(error)) => return Err(error.into()),
+                    Err(_) => return Err(DeckError::Count(count.clone()).into()),
+                },
+                None => 1,
+            };
+            // nothing is drawn unless everything can be
+            if cards > deck.draw.len() + deck.discarded.len() {
+                return Err(DeckError::Exhausted(name.into()).into());
+            }
+            let cards = (0..cards)
+                .map(|_| deck.draw(&mut *state.rng))
+                .collect::<Vec<_>>();
+            state.output.push_str(": ");
+            if count.is_some() {
+                state.output.push('[');
+            }
+            for (index, card) in cards.iter().enumerate() {
+                if index != 0 {
+                    state.output.push_str(", ");
+                }
+                state.output.push_str(&card.name);
+            }
+            if count.is_some() {
+                state.output.push(']');
+            }
+            let mut values = cards.into_iter().map(Card::into_value);
+            Ok(match count {
+                Some(_) => values.collect(),
+                None => values.next().expect("one card should have been drawn"),
+            })
+        }
+        "discard" => {
+            deck.discard();
+            Ok(Value::from(deck.draw.len()))
+        }
+        "reshuffle" => {
+            deck.reshuffle(&mut *state.rng);
+            Ok(Value::from(deck.draw.len()))
+        }
+        _ => {
+            deck.shuffle(&mut *state.rng);
+            Ok(Value::from(deck.draw.len()))
+        }
+    }
+}
+
+/// Named decks of cards that persist between evaluations, so that cards aren't drawn twice until they're shuffled back in.
+///
+/// The `standard` deck of 52 cards and two jokers and the `tarot` deck of 78 cards are created and shuffled when first used.
+#[derive(Debug, Clone, Default)]
+pub struct Decks {
+    decks: BTreeMap<Box<str>, Deck>,
+}
+
+impl Decks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse decks written as their name in brackets followed by a line for every card, such as `[tarot]` and `draw: the fool = 0`.
+    ///
+    /// Cards are in the `draw`, `drawn` or `discarded` pile, with the top of the draw pile first.
+    /// A card's value defaults to its position within its deck, counting from one.
+    /// Blank lines and anything following a `#` are ignored.
+    pub fn parse(source: &str) -> Result<Self, ParseDecksError> {
+        let mut decks = BTreeMap::new();
+        let mut current = None;
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let malformed = || ParseDecksError::Malformed { line: index + 1 };
+            if let Some(name) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                if !crate::Variables::is_name(name) {
+                    return Err(malformed());
+                }
+                if decks.insert(name.into(), Deck::default()).is_some() {
+                    return Err(ParseDecksError::Repeated(name.into()));
+                }
+                current = Some(name);
+                continue;
+            }
+            let deck = current
+                .and_then(|name| decks.get_mut(name))
+                .ok_or_else(malformed)?;
+            let (pile, card) = line.split_once(':').ok_or_else(malformed)?;
+            let (name, value) = match card.rsplit_once('=') {
+                Some((name, value)) => (name, value.trim().parse().map_err(|_| malformed())?),
+                None => (card, BigInt::from(deck.len() + 1)),
+            };
+            let card = Card {
+                name: name.trim().into(),
+                value,
+            };
+            match pile.trim() {
+                // the top of the draw pile is written first but kept last
+                "draw" => deck.draw.insert(0, card),
+                "drawn" => deck.drawn.push(card),
+                "discarded" => deck.discarded.push(card),
+                _ => return Err(malformed()),
+            }
+        }
+        Ok(Self { decks })
+    }
+
+    fn get_or_insert(&mut self, name: &str, rng: &mut impl Rng) -> Result<&mut Deck, DeckError> {
+        if !self.decks.contains_key(name) {
+            let mut deck = match name {
+                "standard" => Deck::standard(),
+                "tarot" => Deck::tarot(),
+                _ => return Err(DeckError::Undefined(name.into())),
+            };
+            deck.shuffle(rng);
+            self.decks.insert(name.into(), deck);
+        }
+        Ok(self
+            .decks
+            .get_mut(name)
+            .expect("deck should have been inserted"))
+    }
+}
+
+impl fmt::Display for Decks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (name, deck)) in self.decks.iter().enumerate() {
+            if index != 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{name}]")?;
+            let Deck {
+                draw,
+                drawn,
+                discarded,
+            } = deck;
+            let piles = draw
+                .iter()
+                .rev()
+                .map(|card| ("draw", card))
+                .chain(drawn.iter().map(|card| ("drawn", card)))
+                .chain(discarded.iter().map(|card| ("discarded", card)));
+            for (pile, Card { name, value }) in piles {
+                writeln!(f, "{pile}: {name} = {value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Deck {
+    /// Cards left to draw, with the top of the pile last.
+    draw: Vec<Card>,
+    /// Cards that have been drawn and not yet discarded.
+    drawn: Vec<Card>,
+    discarded: Vec<Card>,
+}
+
+impl Deck {
+    fn standard() -> Self {
+        let mut draw = Vec::new();
+        for (value, rank) in (2..).zip(RANKS) {
+            for suit in SUITS {
+                draw.push(Card::new(format!("{rank} of {suit}"), value));
+            }
+        }
+        // jokers are wild and beat every other card
+        draw.push(Card::new("red joker".into(), 15));
+        draw.push(Card::new("black joker".into(), 15));
+        Self {
+            draw,
+            ..Self::default()
+        }
+    }
+
+    fn tarot() -> Self {
+        let mut draw = (0..)
+            .zip(MAJOR_ARCANA)
+            .map(|(value, name)| Card::new(name.into(), value))
+            .collect::<Vec<_>>();
+        for suit in MINOR_SUITS {
+            for (value, rank) in (1..).zip(MINOR_RANKS) {
+                draw.push(Card::new(format!("{rank} of {suit}"), value));
+            }
+        }
+        Self {
+            draw,
+            ..Self::default()
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.draw.len() + self.drawn.len() + self.discarded.len()
+    }
+
+    /// Draw the top card, reshuffling the discard pile into the draw pile first if it's empty.
+    fn draw(&mut self, rng: &mut impl Rng) -> Card {
+        if self.draw.is_empty() {
+            self.reshuffle(rng);
+        }
+        let card = self
+            .draw
+            .pop()
+            .expect("deck should have been checked for cards");
+        self.drawn.push(card.clone());
+        card
+    }
+
+    /// Put every drawn card on the discard pile.
+    fn discard(&mut self) {
+        self.discarded.append(&mut self.drawn);
+    }
+
+    /// Shuffle the discard pile back into the draw pile.
+    fn reshuffle(&mut self, rng: &mut impl Rng) {
+        self.draw.append(&mut self.discarded);
+        self.draw.shuffle(rng);
+    }
+
+    /// Shuffle every card back into the draw pile, including those that are drawn.
+    fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.discard();
+        self.reshuffle(rng);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Card {
+    name: Box<str>,
+    value: BigInt,
+}
+
+impl Card {
+    fn new(name: String, value: u32) -> Self {
+        Self {
+            name: name.into(),
+            value: BigInt::from(value),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        let Self { name, value } = self;
+
+        Value::Labeled(Box::new(Value::from(value)), name)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseDecksError {
+    Malformed { line: usize },
+    Repeated(Box<str>),
+}
+
+impl fmt::Display for ParseDecksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { line } => write!(f, "malformed deck on line {line}"),
+            Self::Repeated(name) => write!(f, "repeated deck `{name}`"),
+        }
+    }
+}
+
+impl std::error::Error for ParseDecksError {}
+
+#[derive(Debug)]
+pub enum DeckError {
+    Undefined(Box<str>),
+    Exhausted(Box<str>),
+    Count(Value),
+}
+
+impl DeckError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Undefined(_) => "undefined_deck",
+            Self::Exhausted(_) => "deck_exhausted",
+            Self::Count(_) => "invalid_card_count",
+        }
+    }
+}
+
+impl From<DeckError> for EvalError {
+    fn from(error: DeckError) -> Self {
+        Self::Deck(error)
+    }
+}
+
+impl fmt::Display for DeckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "undefined deck `{name}`"),
+            Self::Exhausted(name) => write!(f, "not enough cards left in deck `{name}`"),
+            Self::Count(count) => write!(f, "cannot draw {count} cards"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng as _};
+
+    #[test]
+    fn round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut decks = Decks::new();
+        let standard = decks.get_or_insert("standard", &mut rng).unwrap();
+        for _ in 0..5 {
+            standard.draw(&mut rng);
+        }
+        standard.discard();
+        standard.draw(&mut rng);
+        decks.get_or_insert("tarot", &mut rng).unwrap();
+        let written = decks.to_string();
+        let parsed = Decks::parse(&written).unwrap();
+        assert_eq!(parsed.to_string(), written);
+        let standard = &parsed.decks["standard"];
+        assert_eq!(
+            (
+                standard.draw.len(),
+                standard.drawn.len(),
+                standard.discarded.len()
+            ),
+            (48, 1, 5)
+        );
+    }
+
+    #[test]
+    fn default_values() {
+        let decks = Decks::parse("[coins]\ndraw: heads\ndraw: tails = 5 # comment\n").unwrap();
+        assert_eq!(
+            decks.to_string(),
+            "[coins]\ndraw: heads = 1\ndraw: tails = 5\n"
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        for (source, line) in [
+            ("draw: orphan = 1", 1),
+            ("[deck]\n\nhand: card", 3),
+            ("[deck]\ndraw: card = many", 2),
+            ("[two words]", 1),
+        ] {
+            assert!(
+                matches!(Decks::parse(source), Err(ParseDecksError::Malformed { line: found }) if found == line),
+                "{source:?}"
+            );
+        }
+        assert!(matches!(
+            Decks::parse("[deck]\n[deck]"),
+            Err(ParseDecksError::Repeated(name)) if &*name == "deck"
+        ));
+    }
+}
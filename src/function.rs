@@ -0,0 +1,163 @@
+use super::{
+    list::ListError,
+    value::{Type, TypeError},
+    EvalError, PartialResult, State, Token, Value,
+};
+use num::BigRational;
+use rand::Rng;
+use std::fmt;
+
+/// Parse the arguments of a call to the function `name` and apply it.
+pub fn main(state: &mut State<impl Rng>, name: &str) -> PartialResult {
+    if !state.lexer.matches(Token::OpeningParenthesis) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push_str(name);
+    state.output.push('(');
+    let value = if name == "repeat" {
+        // the expression can't be evaluated before the count is known
+        let count = super::expression(state)?;
+        if !state.lexer.matches(Token::Comma) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        state.output.push_str(", ");
+        super::list::repetitions(state, count, super::expression)?
+    } else if name == "natural" {
+        natural(state)?
+    } else if name == "double" {
+        // nested doubling is the same as doubling once
+        let doubling = std::mem::replace(&mut state.doubling, true);
+        let result = super::expression(state);
+        state.doubling = doubling;
+        result?
+    } else if matches!(name, "resist" | "vulnerable" | "immune") {
+        super::damage::modify(state, name)?
+    } else if name == "cortex" {
+        super::roll::cortex(state)?
+    } else if name == "savage" {
+        super::roll::savage(state)?
+    } else if name == "narrative" {
+        super::roll::narrative(state)?
+    } else if name == "blades" {
+        super::roll::blades(state)?
+    } else if name == "step" {
+        super::step::main(state)?
+    } else if name == "sets" {
+        super::sets::main(state)?
+    } else if name == "ore" {
+        super::sets::ore(state)?
+    } else if name == "table" {
+        super::table::main(state)?
+    } else if matches!(name, "draw" | "discard" | "reshuffle" | "shuffle") {
+        super::deck::main(state, name)?
+    } else {
+        let mut args = vec![super::expression(state)?];
+        while state.lexer.matches(Token::Comma) {
+            state.output.push_str(", ");
+            args.push(super::expression(state)?);
+        }
+        state.checked(apply(name, args), Value::zero)?
+    };
+    if !state.lexer.matches(Token::ClosingParenthesis) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push(')');
+    Ok(value)
+}
+
+/// Sum of the dice rolled by an expression, without anything added to them.
+fn natural(state: &mut State<impl Rng>) -> PartialResult {
+    let start = state.pools.len();
+    super::expression(state)?;
+    if state.skipping {
+        return Ok(Value::zero());
+    }
+    Ok(state.pools[start..]
+        .iter()
+        .map(super::roll::Pool::value)
+        .try_fold(Value::zero(), Value::add)?)
+}
+
+fn apply(name: &str, mut args: Vec<Value>) -> PartialResult {
+    let function: fn(Vec<Value>) -> PartialResult = match name {
+        "sum" => sum,
+        "sort" => sort,
+        "max" => max,
+        "min" => min,
+        "count" => count,
+        _ => return Err(FunctionError::Undefined(name.into()).into()),
+    };
+    // functions of lists may also be called with the elements as their arguments, including a single element
+    let elements = match args.pop() {
+        Some(arg) if args.is_empty() && arg.ty() == Type::List => arg.into_list()?.into_vec(),
+        Some(arg) => {
+            args.push(arg);
+            args
+        }
+        None => args,
+    };
+    function(elements)
+}
+
+fn sum(elements: Vec<Value>) -> PartialResult {
+    Ok(elements.into_iter().try_fold(Value::zero(), Value::add)?)
+}
+
+fn sort(elements: Vec<Value>) -> PartialResult {
+    let mut elements = numbered(elements)?;
+    elements.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(elements.into_iter().map(|(_, element)| element).collect())
+}
+
+fn max(elements: Vec<Value>) -> PartialResult {
+    let greatest = numbered(elements)?
+        .into_iter()
+        .max_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(greatest.ok_or(ListError::Empty)?.1)
+}
+
+fn min(elements: Vec<Value>) -> PartialResult {
+    let least = numbered(elements)?
+        .into_iter()
+        .min_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(least.ok_or(ListError::Empty)?.1)
+}
+
+/// Pair each element with the number it's ordered by, so that labeled values and damage are ordered like their totals.
+fn numbered(elements: Vec<Value>) -> Result<Vec<(BigRational, Value)>, TypeError> {
+    elements
+        .into_iter()
+        .map(|element| Ok((element.as_number()?.clone(), element)))
+        .collect()
+}
+
+fn count(elements: Vec<Value>) -> PartialResult {
+    Ok(Value::from(elements.len()))
+}
+
+#[derive(Debug)]
+pub enum FunctionError {
+    Undefined(Box<str>),
+}
+
+impl FunctionError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Undefined(_) => "undefined_function",
+        }
+    }
+}
+
+impl From<FunctionError> for EvalError {
+    fn from(error: FunctionError) -> Self {
+        Self::Function(error)
+    }
+}
+
+impl fmt::Display for FunctionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "undefined function `{name}`"),
+        }
+    }
+}
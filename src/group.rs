@@ -0,0 +1,150 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use crate::value::NaturalError;
+use rand::Rng;
+use std::{cell::OnceCell, fmt};
+
+/// Parse `{{expression}, ...}{modifier}*`, summing the members that aren't dropped.
+///
+/// Members are dropped based on their values in the same way that rolls are, so `{1d20+5, 1d20+3}kh1` keeps the greater of the two.
+pub fn main(state: &mut State<impl Rng>) -> Option<PartialResult> {
+    if !state.lexer.matches(Token::OpeningBrace) {
+        return None;
+    }
+    Some(inner(state))
+}
+
+fn inner(state: &mut State<impl Rng>) -> PartialResult {
+    let start = state.output.len();
+    let mut members = Vec::new();
+    loop {
+        let value = super::expression(state)?;
+        members.push((value, state.output.split_off(start)));
+        if !state.lexer.matches(Token::Comma) {
+            break;
+        }
+    }
+    if !state.lexer.matches(Token::ClosingBrace) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    let drop_least = OnceCell::new();
+    let drop_greatest = OnceCell::new();
+    let keep_greatest = OnceCell::new();
+    let keep_least = OnceCell::new();
+    let map = [
+        (Token::DropLeast, "dl", &drop_least),
+        (Token::DropGreatest, "dg", &drop_greatest),
+        (Token::KeepGreatest, "kh", &keep_greatest),
+        (Token::KeepLeast, "kl", &keep_least),
+    ];
+    'outer: loop {
+        for (token, key, cell) in map {
+            if state.lexer.matches(token) {
+                state.output.push_str(key);
+                let arg = super::primary(state)?;
+                let arg = state.checked(natural(&arg), || 0)?;
+                if cell.set(arg).is_err() {
+                    return Err(GroupError::RepeatedModifier.into());
+                }
+                continue 'outer;
+            }
+        }
+        break;
+    }
+    let modifiers = state.output.split_off(start);
+    let len = members.len();
+    let drop_least = [
+        drop_least.into_inner(),
+        keep_greatest
+            .into_inner()
+            .map(|keep| len.saturating_sub(keep)),
+    ];
+    let drop_greatest = [
+        drop_greatest.into_inner(),
+        keep_least.into_inner().map(|keep| len.saturating_sub(keep)),
+    ];
+    let drop_least = drop_least.into_iter().flatten().max().unwrap_or(0);
+    let drop_greatest = drop_greatest.into_iter().flatten().max().unwrap_or(0);
+    let dropped = dropped(&members, drop_least, drop_greatest)?;
+    state.output.push('{');
+    let mut sum = Value::zero();
+    for (index, ((value, text), dropped)) in members.into_iter().zip(dropped).enumerate() {
+        if index != 0 {
+            state.output.push_str(", ");
+        }
+        if dropped {
+            state.output.push('~');
+            state.output.push_str(&text);
+            state.output.push('~');
+        } else {
+            state.output.push_str(&text);
+            sum = sum.add(value)?;
+        }
+    }
+    state.output.push('}');
+    state.output.push_str(&modifiers);
+    Ok(sum)
+}
+
+/// Mark which members are amongst the `drop_least` least or `drop_greatest` greatest.
+fn dropped(
+    members: &[(Value, String)],
+    drop_least: usize,
+    drop_greatest: usize,
+) -> Result<Vec<bool>, EvalError> {
+    let values = members
+        .iter()
+        .map(|(value, _)| value.as_number())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&index| values[index]);
+    let mut dropped = vec![false; values.len()];
+    let least = order.iter().take(drop_least);
+    let greatest = order.iter().rev().take(drop_greatest);
+    for &index in least.chain(greatest) {
+        dropped[index] = true;
+    }
+    Ok(dropped)
+}
+
+// since there can only be at most `usize::MAX` members, arguments need to only be at most `usize::MAX` too
+fn natural(value: &Value) -> Result<usize, EvalError> {
+    let natural = value.to_natural().map_err(|error| match error {
+        NaturalError::Negative | NaturalError::Fractional => {
+            EvalError::from(GroupError::InvalidModifierArgument)
+        }
+        NaturalError::Type(error) => EvalError::from(error),
+    })?;
+    Ok(usize::try_from(natural).unwrap_or(usize::MAX))
+}
+
+#[derive(Debug)]
+pub enum GroupError {
+    RepeatedModifier,
+    InvalidModifierArgument,
+}
+
+impl GroupError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RepeatedModifier => "repeated_modifier",
+            Self::InvalidModifierArgument => "invalid_modifier_argument",
+        }
+    }
+}
+
+impl From<GroupError> for EvalError {
+    fn from(error: GroupError) -> Self {
+        Self::Group(error)
+    }
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RepeatedModifier => write!(f, "repeated modifier"),
+            Self::InvalidModifierArgument => {
+                write!(f, "modifier argument isn't a non-negative integer")
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use super::{
+    roll::{Die, Pool},
+    Decks, EvalError, Evaluation, Variables,
+};
+use num::{BigInt, BigUint};
+use rand::Rng;
+use std::fmt;
+
+/// Evaluation kept around along with its random number generator so that its dice can be changed afterwards, as when pushing a roll in Year Zero games.
+///
+/// Dice are numbered from zero across every roll of the expression in the order they're displayed.
+/// After each change the expression is evaluated again with the changed dice, so that anything added to them or compared against them is too.
+/// Only expressions made up of plain rolls such as `3d6cg5 + 2` can be followed up, and any cards they draw are drawn again from fresh decks.
+#[derive(Debug)]
+pub struct RollHandle<'a, R> {
+    evaluation: Evaluation,
+    variables: &'a Variables,
+    rng: R,
+}
+
+impl<'a, R: Rng> RollHandle<'a, R> {
+    /// Evaluate an expression which may refer to `variables`, keeping hold of `rng` for later changes.
+    pub fn new(raw: &str, variables: &'a Variables, mut rng: R) -> Result<Self, EvalError> {
+        let evaluation = super::evaluate_with(raw, variables, &mut rng)?;
+        Ok(Self::from_evaluation(evaluation, variables, rng))
+    }
+
+    /// Follow up on an expression that's already been evaluated.
+    pub fn from_evaluation(evaluation: Evaluation, variables: &'a Variables, rng: R) -> Self {
+        Self {
+            evaluation,
+            variables,
+            rng,
+        }
+    }
+
+    pub fn evaluation(&self) -> &Evaluation {
+        &self.evaluation
+    }
+
+    pub fn into_evaluation(self) -> Evaluation {
+        self.evaluation
+    }
+
+    pub fn into_rng(self) -> R {
+        self.rng
+    }
+
+    /// Roll again every die that's neither a success, showing its greatest face, nor a bane, showing a one.
+    pub fn push(&mut self) -> Result<&Evaluation, EvalError> {
+        self.follow_up(|pool, dice, index, rng| {
+            if pool.is_pushable(&dice[index]) {
+                dice[index] = pool.reroll(dice, index, rng);
+            }
+            Ok(())
+        })
+    }
+
+    /// Roll the given dice again, such as when spending a luck point.
+    pub fn reroll(&mut self, dice: &[usize]) -> Result<&Evaluation, EvalError> {
+        self.check(dice)?;
+        let mut number = 0;
+        self.follow_up(|pool, rolled, index, rng| {
+            if dice.contains(&number) {
+                rolled[index] = pool.reroll(rolled, index, rng);
+            }
+            number += 1;
+            Ok(())
+        })
+    }
+
+    /// Replace a die with one showing the given face, after which its roll's modifiers are applied.
+    ///
+    /// The face must be on the die, and can't be shown by another die of a roll without replacement.
+    pub fn replace(&mut self, die: usize, face: BigUint) -> Result<&Evaluation, EvalError> {
+        self.check(&[die])?;
+        let mut number = 0;
+        self.follow_up(|pool, dice, index, _| {
+            if number == die {
+                if !pool.can_show(dice, index, &face) {
+                    return Err(FollowUpError::Face(face.clone()));
+                }
+                dice[index] = pool.replace(face.clone());
+            }
+            number += 1;
+            Ok(())
+        })
+    }
+
+    /// Add to the result of a die, such as when spending a resource, without changing the face it came up on.
+    ///
+    /// Results can't go below zero.
+    pub fn bump(&mut self, die: usize, amount: &BigInt) -> Result<&Evaluation, EvalError> {
+        self.check(&[die])?;
+        let mut number = 0;
+        self.follow_up(|_, dice, index, _| {
+            if number == die {
+                let bumped = &mut dice[index];
+                bumped.face = (BigInt::from(bumped.face.clone()) + amount)
+                    .try_into()
+                    .unwrap_or_default();
+            }
+            number += 1;
+            Ok(())
+        })
+    }
+
+    /// Fail unless every die is amongst those of the evaluation.
+    fn check(&self, dice: &[usize]) -> Result<(), EvalError> {
+        let len = self.dice()?.iter().map(Vec::len).sum::<usize>();
+        match dice.iter().find(|&&die| die >= len) {
+            Some(&die) => Err(FollowUpError::UndefinedDie { die, len }.into()),
+            None => Ok(()),
+        }
+    }
+
+    fn dice(&self) -> Result<Vec<Vec<Die>>, EvalError> {
+        self.evaluation
+            .pools
+            .iter()
+            .map(|pool| pool.dice().ok_or(FollowUpError::Unsupported.into()))
+            .collect()
+    }
+
+    /// Change each of the dice of every roll in turn and evaluate the expression again with them.
+    fn follow_up(
+        &mut self,
+        mut change: impl FnMut(&Pool, &mut [Die], usize, &mut R) -> Result<(), FollowUpError>,
+    ) -> Result<&Evaluation, EvalError> {
+        let mut dice = self.dice()?;
+        for (pool, dice) in self.evaluation.pools.iter().zip(&mut dice) {
+            for index in 0..dice.len() {
+                change(pool, dice, index, &mut self.rng)?;
+            }
+        }
+        self.evaluation = super::evaluate_at(
+            &self.evaluation.input,
+            self.variables,
+            &mut Decks::new(),
+            &mut self.rng,
+            0,
+            Some(dice.into_iter()),
+        )?;
+        Ok(&self.evaluation)
+    }
+}
+
+#[derive(Debug)]
+pub enum FollowUpError {
+    /// The expression rolls something other than plain rolls.
+    Unsupported,
+    UndefinedDie {
+        die: usize,
+        len: usize,
+    },
+    /// The face isn't on the die or is already shown by another die of a roll without replacement.
+    Face(BigUint),
+}
+
+impl FollowUpError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Unsupported => "follow_up_unsupported",
+            Self::UndefinedDie { .. } => "undefined_die",
+            Self::Face(_) => "invalid_face",
+        }
+    }
+}
+
+impl From<FollowUpError> for EvalError {
+    fn from(error: FollowUpError) -> Self {
+        Self::FollowUp(error)
+    }
+}
+
+impl fmt::Display for FollowUpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported => write!(f, "only plain rolls can be followed up"),
+            Self::UndefinedDie { len, .. } => write!(f, "only {len} dice to choose from"),
+            Self::Face(face) => write!(f, "die cannot show {face}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::Zero as _;
+    use rand::{rngs::StdRng, SeedableRng as _};
+
+    #[test]
+    fn numbering_is_stable() {
+        let variables = Variables::new();
+        let mut handle = RollHandle::new("6d6dl2", &variables, StdRng::seed_from_u64(0)).unwrap();
+        let output = handle.evaluation().output.clone();
+        for die in 0..6 {
+            handle.bump(die, &BigInt::zero()).unwrap();
+            assert_eq!(handle.evaluation().output, output);
+        }
+
+        let mut handle = RollHandle::new("6d6", &variables, StdRng::seed_from_u64(0)).unwrap();
+        let before = handle.dice().unwrap().concat();
+        handle.replace(3, BigUint::from(6u32)).unwrap();
+        let after = handle.dice().unwrap().concat();
+        for (number, (before, after)) in before.iter().zip(&after).enumerate() {
+            if number == 3 {
+                assert_eq!(after.rolled, BigUint::from(6u32));
+            } else {
+                assert_eq!(before, after, "die {number}");
+            }
+        }
+    }
+}
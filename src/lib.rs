@@ -1,62 +1,311 @@
+mod compare;
+mod damage;
+mod deck;
+mod function;
+mod group;
+mod handle;
+mod json;
+mod list;
 mod number;
+mod outcome;
+mod record;
 mod roll;
+mod sets;
+mod step;
+mod table;
+mod template;
 mod utils;
 mod value;
+mod variable;
+mod versus;
 
 use logos::Logos;
 use rand::Rng;
-use std::{
-    convert::Infallible,
-    fmt::{self, Write as _},
-};
-use value::Value;
+use std::{convert::Infallible, fmt};
+
+pub use compare::{compare, Comparison};
+pub use deck::{Decks, ParseDecksError};
+pub use handle::RollHandle;
+pub use json::Json;
+pub use step::{ParseStepsError, Steps};
+pub use table::{LoadTablesError, ParseTableError, Table, Tables};
+pub use template::TemplateStyle;
+pub use value::{Damage, Fields, NaturalError, Symbols, Type, TypeError, Value};
+pub use variable::Variables;
 
 type PartialResult = Result<Value, EvalError>;
 
 // todo implement passing constraints to constrain behavior when evaluating untrusted user input
 pub fn eval(raw: &str, rng: &mut impl Rng) -> Result<String, EvalError> {
+    Ok(evaluate(raw, rng)?.to_string())
+}
+
+/// Replace every `[[{expression}]]` within text with its result, such as `The chest holds [[3d6*10]] gold`.
+pub fn eval_template(
+    raw: &str,
+    style: TemplateStyle,
+    rng: &mut impl Rng,
+) -> Result<String, EvalError> {
+    eval_template_with(raw, &Variables::new(), &mut Decks::new(), style, rng)
+}
+
+/// Replace every `[[{expression}]]` within text with its result, where expressions may refer to `variables` and draw from `decks`.
+pub fn eval_template_with(
+    raw: &str,
+    variables: &Variables,
+    decks: &mut Decks,
+    style: TemplateStyle,
+    rng: &mut impl Rng,
+) -> Result<String, EvalError> {
+    template::main(raw, variables, decks, style, rng)
+}
+
+pub fn evaluate(raw: &str, rng: &mut impl Rng) -> Result<Evaluation, EvalError> {
+    evaluate_with(raw, &Variables::new(), rng)
+}
+
+/// Evaluate an expression which may refer to `variables`.
+pub fn evaluate_with(
+    raw: &str,
+    variables: &Variables,
+    rng: &mut impl Rng,
+) -> Result<Evaluation, EvalError> {
+    evaluate_with_decks(raw, variables, &mut Decks::new(), rng)
+}
+
+/// Evaluate an expression which may refer to `variables` and draw from `decks`, which keep their state for later evaluations.
+///
+/// The decks are left as they were if evaluation fails.
+pub fn evaluate_with_decks(
+    raw: &str,
+    variables: &Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+) -> Result<Evaluation, EvalError> {
+    evaluate_at(raw, variables, decks, rng, 0, None)
+}
+
+/// Evaluate an expression embedded `depth` tables deep, taking the dice of its plain rolls from `replay` where they fit.
+fn evaluate_at(
+    raw: &str,
+    variables: &Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    depth: usize,
+    replay: Option<roll::Replay>,
+) -> Result<Evaluation, EvalError> {
+    let mut drawn = decks.clone();
     let mut state = State {
         lexer: Lexer::new(raw),
         output: String::new(),
+        pools: Vec::new(),
+        variables,
+        decks: &mut drawn,
+        skipping: false,
+        doubling: false,
+        depth,
+        replay,
         rng,
     };
     let value = expression(&mut state)?;
     let State {
-        lexer, mut output, ..
+        lexer,
+        output,
+        pools,
+        ..
     } = state;
     if !lexer.is_eos() {
         return Err(EvalError::UnexpectedToken);
     }
-    write!(output, " = {value}")?;
-    Ok(output)
+    *decks = drawn;
+    Ok(Evaluation {
+        input: raw.into(),
+        output,
+        value,
+        pools,
+    })
 }
 
 #[derive(Debug)]
-struct State<'a, 'b, R> {
+struct State<'a, 'b, 'c, R> {
     lexer: Lexer<'a>,
     output: String,
+    pools: Vec<roll::Pool>,
+    variables: &'c Variables,
+    decks: &'c mut Decks,
+    /// Whether the expression being parsed is in a branch that isn't taken.
+    skipping: bool,
+    /// Whether rolls are made with twice as many dice, as when doubling damage.
+    doubling: bool,
+    /// Number of tables that the expression is embedded in.
+    depth: usize,
+    /// Dice to use in place of rolling plain rolls, when following up on a previous evaluation.
+    replay: Option<roll::Replay>,
     rng: &'b mut R,
 }
 
+impl<R> State<'_, '_, '_, R> {
+    /// Result of an operation, which whilst skipping only fails on a type error.
+    ///
+    /// Skipped operations work on placeholders, so anything else that goes wrong depends on values that aren't known and gives a placeholder instead.
+    fn checked<T, E: Into<EvalError>>(
+        &self,
+        result: Result<T, E>,
+        placeholder: impl FnOnce() -> T,
+    ) -> Result<T, EvalError> {
+        match result.map_err(Into::into) {
+            Err(EvalError::Value(value::ValueError::Type(error))) => Err(error.into()),
+            Err(_) if self.skipping => Ok(placeholder()),
+            result => result,
+        }
+    }
+}
+
+/// Result of evaluating an expression along with every roll that went into it.
+#[derive(Debug)]
+pub struct Evaluation {
+    input: Box<str>,
+    output: String,
+    value: Value,
+    pools: Vec<roll::Pool>,
+}
+
+impl Evaluation {
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Whether the value is true or a non-zero number.
+    pub fn is_true(&self) -> bool {
+        self.value.is_true()
+    }
+
+    pub fn to_json(&self) -> Json {
+        let Self {
+            input,
+            output,
+            value,
+            pools,
+        } = self;
+
+        Json::object([
+            ("input", Json::from(&**input)),
+            ("output", Json::from(&**output)),
+            ("value", value.to_json()),
+            ("pools", pools.iter().map(roll::Pool::to_json).collect()),
+        ])
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { output, value, .. } = self;
+
+        write!(f, "{output} = {value}")
+    }
+}
+
 #[derive(Debug)]
 pub enum EvalError {
     UnexpectedToken,
+    UndefinedVariable(Box<str>),
+    Deck(deck::DeckError),
+    FollowUp(handle::FollowUpError),
+    Function(function::FunctionError),
+    Group(group::GroupError),
+    List(list::ListError),
+    Record(record::RecordError),
     Roll(roll::RollError),
+    Sets(sets::SetsError),
+    Step(step::StepError),
+    Table(table::TableError),
+    Template(template::TemplateError),
     Value(value::ValueError),
+    Versus(versus::VersusError),
 }
 
 impl fmt::Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::UnexpectedToken => write!(f, "malformed expression"),
-            Self::Roll(error) => write!(f, "parsing roll: {error}"),
+            Self::UndefinedVariable(name) => write!(f, "undefined variable `{name}`"),
+            Self::Deck(error) => write!(f, "drawing cards: {error}"),
+            Self::FollowUp(error) => write!(f, "following up roll: {error}"),
+            Self::Function(error) => write!(f, "calling function: {error}"),
+            Self::Group(error) => write!(f, "evaluating group: {error}"),
+            Self::List(error) => write!(f, "evaluating list: {error}"),
+            Self::Record(error) => write!(f, "evaluating record: {error}"),
+            Self::Roll(error) => write!(f, "{error}"),
+            Self::Sets(error) => write!(f, "matching sets: {error}"),
+            Self::Step(error) => write!(f, "translating step: {error}"),
+            Self::Table(error) => write!(f, "rolling on table: {error}"),
+            Self::Template(error) => write!(f, "filling template: {error}"),
             Self::Value(error) => write!(f, "evaluating expression: {error}"),
+            Self::Versus(error) => write!(f, "resolving outcome: {error}"),
         }
     }
 }
 
 impl std::error::Error for EvalError {}
 
+/// Broad category of an [`EvalError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The expression is malformed.
+    Syntax,
+    /// The expression is well formed but has no value.
+    Evaluation,
+    /// The expression exceeds what can be evaluated.
+    Limit,
+}
+
+impl EvalError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::UnexpectedToken => ErrorClass::Syntax,
+            Self::UndefinedVariable(_) => ErrorClass::Evaluation,
+            Self::Deck(_) => ErrorClass::Evaluation,
+            Self::FollowUp(_) => ErrorClass::Evaluation,
+            Self::Function(_) => ErrorClass::Evaluation,
+            Self::Group(_) => ErrorClass::Evaluation,
+            Self::List(error) => error.class(),
+            Self::Record(_) => ErrorClass::Evaluation,
+            Self::Roll(error) => error.class(),
+            Self::Sets(_) => ErrorClass::Evaluation,
+            Self::Step(error) => error.class(),
+            Self::Table(error) => error.class(),
+            Self::Template(_) => ErrorClass::Syntax,
+            Self::Value(_) => ErrorClass::Evaluation,
+            Self::Versus(_) => ErrorClass::Evaluation,
+        }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let (stage, kind) = match self {
+            Self::UnexpectedToken => ("syntax", "unexpected_token"),
+            Self::UndefinedVariable(_) => ("variable", "undefined_variable"),
+            Self::Deck(error) => ("deck", error.kind()),
+            Self::FollowUp(error) => ("follow_up", error.kind()),
+            Self::Function(error) => ("function", error.kind()),
+            Self::Group(error) => ("group", error.kind()),
+            Self::List(error) => ("list", error.kind()),
+            Self::Record(error) => ("record", error.kind()),
+            Self::Roll(error) => ("roll", error.kind()),
+            Self::Sets(error) => ("sets", error.kind()),
+            Self::Step(error) => ("step", error.kind()),
+            Self::Table(error) => ("table", error.kind()),
+            Self::Template(error) => ("template", error.kind()),
+            Self::Value(error) => ("value", error.kind()),
+            Self::Versus(error) => ("versus", error.kind()),
+        };
+        Json::object([
+            ("stage", Json::from(stage)),
+            ("kind", Json::from(kind)),
+            ("message", Json::from(self.to_string())),
+        ])
+    }
+}
+
 impl From<Infallible> for EvalError {
     fn from(error: Infallible) -> Self {
         match error {}
@@ -70,7 +319,84 @@ impl From<fmt::Error> for EvalError {
 }
 
 fn expression(state: &mut State<impl Rng>) -> PartialResult {
-    equality(state)
+    let value = conditional(state)?;
+    if state.lexer.peek(Token::EqualsGreaterThan) {
+        return outcome::main(state, value);
+    }
+    Ok(value)
+}
+
+/// Parse `if {condition} then {expression} else {expression}`, only evaluating the branch that's taken.
+fn conditional(state: &mut State<impl Rng>) -> PartialResult {
+    if !state.lexer.matches(Token::If) {
+        return or(state);
+    }
+    state.output.push_str("if ");
+    let condition = expression(state)?;
+    if !state.lexer.matches(Token::Then) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push_str(" then ");
+    let taken = condition.truth()? || state.skipping;
+    let consequent = if taken {
+        Some(expression(state)?)
+    } else {
+        skip(state, expression)?;
+        None
+    };
+    if !state.lexer.matches(Token::Else) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push_str(" else ");
+    match consequent {
+        Some(value) => {
+            skip(state, expression)?;
+            Ok(value)
+        }
+        None => expression(state),
+    }
+}
+
+/// Parse `rule` without evaluating it, writing its source to the output in place of its evaluation.
+///
+/// Nothing is rolled whilst skipping, so the value produced is a placeholder that only has the type the rule would evaluate to, which is still checked by whatever uses it.
+fn skip<R: Rng>(state: &mut State<R>, rule: fn(&mut State<R>) -> PartialResult) -> PartialResult {
+    let skipping = std::mem::replace(&mut state.skipping, true);
+    let output_len = state.output.len();
+    let start = state.lexer.position();
+    let result = rule(state);
+    state.skipping = skipping;
+    let value = result?;
+    let end = state.lexer.position();
+    state.output.truncate(output_len);
+    state
+        .output
+        .push_str(state.lexer.source()[start..end].trim_end());
+    Ok(value)
+}
+
+macro_rules! logical_rule {
+    ($name:ident -> $next:ident { $token:ident, $repr:literal, short_circuit_on: $short_circuit:literal }) => {
+        fn $name(state: &mut State<impl Rng>) -> PartialResult {
+            let mut left = $next(state)?;
+            while state.lexer.matches(Token::$token) {
+                state.output.push_str($repr);
+                let truth = left.truth()?;
+                if state.skipping {
+                    $next(state)?.truth()?;
+                    continue;
+                }
+                if truth == $short_circuit {
+                    skip(state, $next)?.truth()?;
+                    left = Value::from(truth);
+                } else {
+                    let right = $next(state)?;
+                    left = Value::from(right.truth()?);
+                }
+            }
+            Ok(left)
+        }
+    };
 }
 
 macro_rules! binary_rule {
@@ -87,9 +413,10 @@ macro_rules! binary_rule {
                     $(Op::$token => $repr),*
                 });
                 let right = $next(state)?;
-                left = match op {
-                    $(Op::$token => Value::$func(left, right)?),*
+                let result = match op {
+                    $(Op::$token => Value::$func(left, right)),*
                 };
+                left = state.checked(result, Value::zero)?;
             }
         }
     };
@@ -107,25 +434,34 @@ macro_rules! unary_rule {
                 $(Op::$token => $repr),*
             });
             let right = $name(state)?;
-            Ok(match op {
-                $(Op::$token => Value::$func(right)?),*
-            })
+            let result = match op {
+                $(Op::$token => Value::$func(right)),*
+            };
+            state.checked(result, Value::zero)
         }
     };
 }
 
+logical_rule!(or -> and { VerticalLineVerticalLine, " || ", short_circuit_on: true });
+
+logical_rule!(and -> equality { AmpersandAmpersand, " && ", short_circuit_on: false });
+
 binary_rule!(equality -> comparison {
     Equals => eq, " = ",
     ExclamationPointEquals => ne, " != ",
 });
 
-binary_rule!(comparison -> term {
+binary_rule!(comparison -> versus {
     LessThan => lt, " < ",
     LessThanEquals => le, " <= ",
     GreaterThan => gt, " > ",
     GreaterThanEquals => ge, " >= ",
 });
 
+fn versus(state: &mut State<impl Rng>) -> PartialResult {
+    versus::main(state)
+}
+
 binary_rule!(term -> factor {
     Plus => add, " + ",
     Minus => sub, " - ",
@@ -136,20 +472,56 @@ binary_rule!(factor -> unary {
     Slant => div, "/",
 });
 
-unary_rule!(unary -> primary {
+unary_rule!(unary -> repeat {
     ExclamationPoint => not, "!",
     Minus => neg, "-",
 });
 
+fn repeat(state: &mut State<impl Rng>) -> PartialResult {
+    list::repeat(state)
+}
+
+fn roll(state: &mut State<impl Rng>) -> PartialResult {
+    let value = roll::main(state)?;
+    damage::label(state, value)
+}
+
 fn primary(state: &mut State<impl Rng>) -> PartialResult {
-    if let Some(result) = grouping(state) {
-        Ok(result?)
+    let value = if let Some(result) = grouping(state) {
+        result?
+    } else if let Some(result) = group::main(state) {
+        result?
     } else if let Some(result) = number::main(state) {
-        Ok(result?)
-    } else if let Some(result) = roll::main(state) {
-        Ok(result?)
+        result?
+    } else if let Some(result) = identifier(state) {
+        result?
     } else {
-        Err(EvalError::UnexpectedToken)
+        return Err(EvalError::UnexpectedToken);
+    };
+    postfix(state, value)
+}
+
+/// Parse any number of indices and field accesses following a value.
+fn postfix(state: &mut State<impl Rng>, mut value: Value) -> PartialResult {
+    loop {
+        // a variable alone in brackets is lexed as a label, which indexes lists rather than labeling damage
+        let indexed_by_label = state.lexer.peek(Token::Label) && value.ty() == value::Type::List;
+        value = if state.lexer.peek(Token::OpeningBracket) || indexed_by_label {
+            list::index(state, value)?
+        } else if state.lexer.peek(Token::Period) {
+            record::field(state, value)?
+        } else {
+            return Ok(value);
+        };
+    }
+}
+
+fn identifier(state: &mut State<impl Rng>) -> Option<PartialResult> {
+    let name = state.lexer.matching(Token::Identifier)?;
+    if state.lexer.peek(Token::OpeningParenthesis) {
+        Some(function::main(state, name))
+    } else {
+        Some(variable::main(state, name))
     }
 }
 
@@ -169,7 +541,7 @@ fn grouping(state: &mut State<impl Rng>) -> Option<PartialResult> {
     Some(Ok(value))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Lexer<'a> {
     next: Option<Result<Token, ()>>,
     source: logos::Lexer<'a, Token>,
@@ -186,6 +558,22 @@ impl<'a> Lexer<'a> {
         self.next.is_none()
     }
 
+    /// Offset of the next token within the source.
+    fn position(&self) -> usize {
+        match self.next {
+            Some(_) => self.source.span().start,
+            None => self.source.source().len(),
+        }
+    }
+
+    fn source(&self) -> &'a str {
+        self.source.source()
+    }
+
+    fn peek(&self, token: Token) -> bool {
+        self.next == Some(Ok(token))
+    }
+
     fn advance(&mut self) {
         self.next = self.source.next();
     }
@@ -198,18 +586,31 @@ impl<'a> Lexer<'a> {
         matches
     }
 
-    fn matching(&mut self, token: Token) -> Option<&str> {
+    /// Match an identifier that is exactly the given word.
+    fn matches_word(&mut self, word: &str) -> bool {
+        let matches = self.peek(Token::Identifier) && self.source.slice() == word;
+        if matches {
+            self.advance();
+        }
+        matches
+    }
+
+    fn matching(&mut self, token: Token) -> Option<&'a str> {
         let slice = self.source.slice();
         self.matches(token).then_some(slice)
     }
 }
 
 // https://nvlpubs.nist.gov/nistpubs/Legacy/FIPS/fipspub1-2-1977.pdf
-#[derive(Debug, Logos, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Logos, PartialEq, Eq)]
 #[logos(skip r"\s")]
 enum Token {
     #[token("!")]
     ExclamationPoint,
+    #[token("&&")]
+    AmpersandAmpersand,
+    #[token("||")]
+    VerticalLineVerticalLine,
     #[token("=")]
     Equals,
     #[token("!=")]
@@ -222,6 +623,8 @@ enum Token {
     GreaterThan,
     #[token(">=")]
     GreaterThanEquals,
+    #[token("=>")]
+    EqualsGreaterThan,
     #[token("*")]
     Asterisk,
     #[token("/")]
@@ -234,8 +637,95 @@ enum Token {
     OpeningParenthesis,
     #[token(")")]
     ClosingParenthesis,
+    #[token("[")]
+    OpeningBracket,
+    #[token("]")]
+    ClosingBracket,
+    #[regex(r"\[[a-zA-Z_]+\]")]
+    Label,
+    #[token("{")]
+    OpeningBrace,
+    #[token("}")]
+    ClosingBrace,
+    #[token(",")]
+    Comma,
+    #[token(".")]
+    Period,
+    #[token(":")]
+    Colon,
+    #[regex(r#""[^"]*""#)]
+    Text,
     #[regex(r"\d+(\.\d*)?")]
     Number,
-    #[regex(r"\d*d\d+([a-zA-Z]\d*)*")]
-    Roll,
+    // keywords take priority over identifiers of the same length
+    #[regex(r"[a-zA-Z_]+", priority = 1)]
+    Identifier,
+    #[token("if")]
+    If,
+    #[token("then")]
+    Then,
+    #[token("else")]
+    Else,
+    #[token("x")]
+    X,
+    #[token("vs")]
+    Vs,
+    #[token("d")]
+    D,
+    #[token("dl")]
+    DropLeast,
+    #[token("dg")]
+    DropGreatest,
+    #[token("cg")]
+    CountGreater,
+    #[token("cl")]
+    CountLesser,
+    #[token("cs")]
+    CriticalSuccess,
+    #[token("cf")]
+    CriticalFailure,
+    #[token("kh")]
+    KeepGreatest,
+    #[token("kl")]
+    KeepLeast,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng as _};
+
+    fn type_error(raw: &str) -> bool {
+        let result = evaluate(raw, &mut StdRng::seed_from_u64(0));
+        matches!(result, Err(EvalError::Value(value::ValueError::Type(_))))
+    }
+
+    #[test]
+    fn skipped_types() {
+        assert!(type_error("if 1 = 1 then 1 else (1d6 > 3) + 2d6"));
+        assert!(type_error("if 1 = 2 then (1d6 > 3) + 2d6 else 1"));
+        assert!(type_error("1 < 2 || 1d6"));
+        assert!(type_error("1 > 2 && 1d6"));
+        assert!(type_error("if 1 = 2 then narrative(ability) + 1 else 0"));
+        assert!(type_error("if 1 = 2 then sort(3x1d6) + 1 else 0"));
+        assert!(type_error(
+            "if 1 = 2 then cortex(d8).hitches && 1 = 1 else 0"
+        ));
+        assert!(type_error("if 1 = 2 then 4d6dl(1 = 1) else 0"));
+    }
+
+    #[test]
+    fn skipped_values() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for raw in [
+            "if 1 = 2 then 1d6 / 0 else 1",
+            "if 1 = 2 then (3x1d6)[5] + 1 else 1",
+            "if 1 = 2 then (1d20 vs 15).success && 1 = 1 else 1",
+            "if 1 = 2 then (1d6[fire]).fire + 1 else 1",
+            "1 < 2 || 1d6 > 3",
+        ] {
+            let evaluation = evaluate(raw, &mut rng).unwrap();
+            assert!(evaluation.is_true(), "{raw}");
+        }
+    }
 }
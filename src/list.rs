@@ -0,0 +1,125 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use crate::value::NaturalError;
+use rand::Rng;
+use std::fmt;
+
+/// Parse `{count}x{roll}`, evaluating the roll `count` times.
+pub fn repeat(state: &mut State<impl Rng>) -> PartialResult {
+    let count = super::roll(state)?;
+    if !state.lexer.matches(Token::X) {
+        return Ok(count);
+    }
+    state.output.push('x');
+    repetitions(state, count, super::roll)
+}
+
+/// Evaluate `rule` `count` times from the same position in the source, collecting the values into a list.
+pub fn repetitions<R: Rng>(
+    state: &mut State<R>,
+    count: Value,
+    rule: fn(&mut State<R>) -> PartialResult,
+) -> PartialResult {
+    // a skipped repetition stands in for its elements
+    let count = state.checked(natural(&count, ListError::InvalidCount), || 1)?;
+    if count == 0 {
+        super::skip(state, rule)?;
+        return Ok(Value::from_iter([]));
+    }
+    let start = state.lexer.clone();
+    let mut elements = Vec::new();
+    state.output.push('[');
+    for index in 0..count {
+        if index != 0 {
+            state.lexer = start.clone();
+            state.output.push_str(", ");
+        }
+        elements.push(rule(state)?);
+    }
+    state.output.push(']');
+    Ok(Value::from_iter(elements))
+}
+
+/// Parse `[{index}]` following a value.
+pub fn index(state: &mut State<impl Rng>, value: Value) -> PartialResult {
+    let index = if let Some(label) = state.lexer.matching(Token::Label) {
+        // a variable alone in brackets is lexed as a label
+        let name = label.trim_start_matches('[').trim_end_matches(']');
+        state.output.push('[');
+        let index = super::variable::main(state, name)?;
+        state.output.push(']');
+        index
+    } else {
+        if !state.lexer.matches(Token::OpeningBracket) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        state.output.push('[');
+        let index = super::expression(state)?;
+        if !state.lexer.matches(Token::ClosingBracket) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        state.output.push(']');
+        index
+    };
+    let elements = value.into_list()?;
+    let index = state.checked(natural(&index, ListError::InvalidIndex), || 0)?;
+    let element = elements
+        .into_vec()
+        .into_iter()
+        .nth(index)
+        .ok_or(ListError::IndexOutOfBounds);
+    state.checked(element, Value::zero)
+}
+
+fn natural(value: &Value, invalid: ListError) -> Result<usize, EvalError> {
+    let natural = value.to_natural().map_err(|error| match error {
+        NaturalError::Negative | NaturalError::Fractional => EvalError::from(invalid),
+        NaturalError::Type(error) => EvalError::from(error),
+    })?;
+    Ok(usize::try_from(natural).map_err(|_| ListError::LenExceedsMaximum)?)
+}
+
+#[derive(Debug)]
+pub enum ListError {
+    InvalidCount,
+    InvalidIndex,
+    IndexOutOfBounds,
+    LenExceedsMaximum,
+    Empty,
+}
+
+impl ListError {
+    pub fn class(&self) -> crate::ErrorClass {
+        match self {
+            Self::LenExceedsMaximum => crate::ErrorClass::Limit,
+            _ => crate::ErrorClass::Evaluation,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::InvalidCount => "invalid_count",
+            Self::InvalidIndex => "invalid_index",
+            Self::IndexOutOfBounds => "index_out_of_bounds",
+            Self::LenExceedsMaximum => "len_exceeds_maximum",
+            Self::Empty => "empty",
+        }
+    }
+}
+
+impl From<ListError> for EvalError {
+    fn from(error: ListError) -> Self {
+        Self::List(error)
+    }
+}
+
+impl fmt::Display for ListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCount => write!(f, "repetition count isn't a non-negative integer"),
+            Self::InvalidIndex => write!(f, "index isn't a non-negative integer"),
+            Self::IndexOutOfBounds => write!(f, "index out of bounds"),
+            Self::LenExceedsMaximum => write!(f, "list length exceeds maximum"),
+            Self::Empty => write!(f, "list is empty"),
+        }
+    }
+}
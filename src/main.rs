@@ -1,71 +1,665 @@
 use fcla::prelude::*;
+use num::{BigInt, BigUint};
 use rand::{rngs::StdRng, Rng, SeedableRng as _};
-use std::io::{self, Write as _};
+use roll::{Decks, Json, Variables};
+use std::{
+    convert::Infallible,
+    fmt,
+    io::{self, Write as _},
+    num::NonZeroUsize,
+    process::ExitCode,
+};
 
-fn main() -> fcla::MainResult<io::Error> {
+fn main() -> ExitCode {
     #[derive(FromArgs)]
     struct Args {
-        raw: Option<Box<str>>,
-        seed: Option<[u8; 32]>,
+        command: Command,
+        seed: Option<Seed>,
+        format: Option<Format>,
+        steps: Option<Box<str>>,
+        decks: Option<Box<str>>,
+        tables: Option<Box<str>>,
+        template: Option<Template>,
     }
 
-    let Args { raw, seed } = fcla::parse_cla::<Args>()?.args;
-    let mut rng = match seed {
-        Some(seed) => StdRng::from_seed(seed),
-        None => StdRng::from_entropy(),
+    #[derive(FromArgs)]
+    enum Command {
+        Eval {
+            raw: Option<Box<str>>,
+        },
+        Test {
+            raw: Box<str>,
+        },
+        Compare {
+            left: Box<str>,
+            right: Box<str>,
+            samples: NonZeroUsize,
+        },
+    }
+
+    let Args {
+        command,
+        seed,
+        format,
+        steps,
+        decks: decks_path,
+        tables,
+        template,
+    } = match fcla::parse_cla::<Args>() {
+        Ok(cla) => cla.args,
+        Err(error) => {
+            eprintln!("Error: {:?}", fcla::MainError::<io::Error>::from(error));
+            return Status::Usage.into();
+        }
+    };
+    let seed = seed.unwrap_or_else(|| Seed(rand::random()));
+    eprintln!("Seed: {seed}");
+    let Seed(seed) = seed;
+    let mut rng = StdRng::from_seed(seed);
+    let format = format.unwrap_or(Format::Text);
+    let mut variables = Variables::new();
+    if let Some(path) = steps {
+        match load_steps(&path) {
+            Ok(steps) => variables.set_steps(steps),
+            Err(status) => return status.into(),
+        }
+    }
+    if let Some(path) = tables {
+        match roll::Tables::load(&*path) {
+            Ok(tables) => *variables.tables_mut() = tables,
+            Err(error) => {
+                eprintln!("Error: loading tables: {error}");
+                return match error {
+                    roll::LoadTablesError::Io(_) => Status::Io,
+                    roll::LoadTablesError::Name(_) | roll::LoadTablesError::Parse { .. } => {
+                        Status::Syntax
+                    }
+                }
+                .into();
+            }
+        }
+    }
+    let mut decks = match decks_path.as_deref().map(load_decks).transpose() {
+        Ok(decks) => decks.unwrap_or_default(),
+        Err(status) => return status.into(),
+    };
+    let status = match (command, template) {
+        (Command::Eval { raw: Some(raw) }, Some(template)) => {
+            fill(&raw, &variables, &mut decks, &mut rng, format, template)
+        }
+        (Command::Eval { raw: None }, Some(template)) => io::read_to_string(io::stdin())
+            .and_then(|raw| fill(&raw, &variables, &mut decks, &mut rng, format, template)),
+        (Command::Eval { raw: Some(raw) }, None) => {
+            cla(&raw, &mut variables, &mut decks, &mut rng, format)
+        }
+        (Command::Eval { raw: None }, None) => file(
+            io::stdin().lock(),
+            &mut variables,
+            &mut decks,
+            &mut rng,
+            format,
+        ),
+        (Command::Test { .. } | Command::Compare { .. }, Some(_)) => {
+            eprintln!("Error: only `Eval` fills in templates");
+            return Status::Usage.into();
+        }
+        (Command::Test { raw }, None) => test(&raw, &mut variables, &mut decks, &mut rng, format),
+        (
+            Command::Compare {
+                left,
+                right,
+                samples,
+            },
+            None,
+        ) => compare(&left, &right, samples, &variables, &mut rng, format),
     };
-    match raw {
-        Some(raw) => cla(&raw, &mut rng)?,
-        None => file(io::stdin().lock(), &mut rng)?,
+    let status = match status {
+        Ok(status) => status,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            Status::Io
+        }
+    };
+    if let Some(path) = decks_path {
+        if let Err(error) = std::fs::write(&*path, decks.to_string()) {
+            eprintln!("Error: writing decks: {error}");
+            return Status::Io.into();
+        }
+    }
+    status.into()
+}
+
+/// Process exit status.
+#[derive(Clone, Copy)]
+enum Status {
+    Success = 0,
+    /// A tested expression evaluated to false.
+    False = 1,
+    Usage = 2,
+    Syntax = 3,
+    Evaluation = 4,
+    Limit = 5,
+    Io = 6,
+}
+
+impl From<Status> for ExitCode {
+    fn from(status: Status) -> Self {
+        Self::from(status as u8)
+    }
+}
+
+impl From<&roll::EvalError> for Status {
+    fn from(error: &roll::EvalError) -> Self {
+        match error.class() {
+            roll::ErrorClass::Syntax => Self::Syntax,
+            roll::ErrorClass::Evaluation => Self::Evaluation,
+            roll::ErrorClass::Limit => Self::Limit,
+        }
+    }
+}
+
+#[derive(Clone, Copy, FromArgs)]
+enum Format {
+    Text,
+    Json,
+}
+
+/// How inline expressions of a template are replaced.
+#[derive(Clone, Copy, FromArgs)]
+enum Template {
+    Values,
+    Breakdowns,
+}
+
+/// Read a step table, reporting any failure as the status to exit with.
+fn load_steps(path: &str) -> Result<roll::Steps, Status> {
+    let source = std::fs::read_to_string(path).map_err(|error| {
+        eprintln!("Error: reading step table: {error}");
+        Status::Io
+    })?;
+    roll::Steps::parse(&source).map_err(|error| {
+        eprintln!("Error: parsing step table: {error}");
+        Status::Syntax
+    })
+}
+
+/// Read the decks left by a previous run, starting afresh if there are none.
+fn load_decks(path: &str) -> Result<Decks, Status> {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Decks::new()),
+        Err(error) => {
+            eprintln!("Error: reading decks: {error}");
+            return Err(Status::Io);
+        }
+    };
+    Decks::parse(&source).map_err(|error| {
+        eprintln!("Error: parsing decks: {error}");
+        Status::Syntax
+    })
+}
+
+fn cla(
+    raw: &str,
+    variables: &mut Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    format: Format,
+) -> io::Result<Status> {
+    Ok(
+        match eval(raw, None, variables, decks, rng, format, io::stdout())? {
+            Ok(_) => Status::Success,
+            Err(status) => status,
+        },
+    )
+}
+
+fn test(
+    raw: &str,
+    variables: &mut Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    format: Format,
+) -> io::Result<Status> {
+    Ok(
+        match eval(raw, None, variables, decks, rng, format, io::stdout())? {
+            Ok(true) => Status::Success,
+            Ok(false) => Status::False,
+            Err(status) => status,
+        },
+    )
+}
+
+/// Evaluate a roll sheet where each line is an optionally labelled expression (`label: expression`).
+///
+/// Labels that are valid variable names can be referred to by later lines.
+/// Lines starting with `>` follow up on the dice of the last line that succeeded, such as `> push` or `> reroll 1 3`.
+/// Blank lines and anything following a `#` are ignored.
+/// The status is that of the first failing line, if any.
+fn file(
+    source: impl io::BufRead,
+    variables: &mut Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    format: Format,
+) -> io::Result<Status> {
+    let mut output = io::stdout().lock();
+    let mut failures = Vec::new();
+    let mut status = Status::Success;
+    let mut last = None::<(roll::Evaluation, Option<String>)>;
+    for (index, line) in source.lines().enumerate() {
+        let line = line?;
+        let line = uncommented(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let outcome = if let Some(action) = line.trim().strip_prefix('>') {
+            let Some((evaluation, label)) = last.take() else {
+                let entry = Entry {
+                    number: index + 1,
+                    label: None,
+                };
+                let error = "nothing to follow up";
+                fail(line.trim(), entry, "no_roll", error, format, &mut output)?;
+                if failures.is_empty() {
+                    status = Status::Evaluation;
+                }
+                failures.push(entry.number);
+                continue;
+            };
+            let entry = Entry {
+                number: index + 1,
+                label: label.as_deref(),
+            };
+            let Some(action) = FollowUp::parse(action) else {
+                let error = "malformed follow-up";
+                fail(
+                    action.trim(),
+                    entry,
+                    "malformed",
+                    error,
+                    format,
+                    &mut output,
+                )?;
+                if failures.is_empty() {
+                    status = Status::Syntax;
+                }
+                failures.push(entry.number);
+                last = Some((evaluation, label));
+                continue;
+            };
+            let mut handle = roll::RollHandle::from_evaluation(evaluation, variables, &mut *rng);
+            let result = action.apply(&mut handle);
+            let evaluation = handle.into_evaluation();
+            let result = result.as_ref().map(|_| &evaluation);
+            let outcome = report(
+                action.raw,
+                result,
+                Some(entry),
+                variables,
+                format,
+                &mut output,
+            )?;
+            // a failed follow-up leaves the dice as they were for the next
+            last = Some((evaluation, label));
+            outcome
+        } else {
+            let (label, raw) = labeled(line);
+            let entry = Entry {
+                number: index + 1,
+                label,
+            };
+            let result = roll::evaluate_with_decks(raw, variables, decks, rng);
+            let outcome = report(
+                raw,
+                result.as_ref(),
+                Some(entry),
+                variables,
+                format,
+                &mut output,
+            )?;
+            if let Ok(evaluation) = result {
+                last = Some((evaluation, label.map(String::from)));
+            }
+            outcome
+        };
+        if let Err(failure) = outcome {
+            if failures.is_empty() {
+                status = failure;
+            }
+            failures.push(index + 1);
+        }
+    }
+    if failures.is_empty() {
+        return Ok(status);
+    }
+    let mut error = io::stderr().lock();
+    write!(error, "{} line(s) failed:", failures.len())?;
+    for number in failures {
+        write!(error, " {number}")?;
+    }
+    writeln!(error)?;
+    Ok(status)
+}
+
+/// Change to the dice of the last roll sheet line, written after a `>`.
+struct FollowUp<'a> {
+    raw: &'a str,
+    action: Action,
+}
+
+enum Action {
+    Push,
+    /// Dice numbered from one.
+    Reroll(Vec<usize>),
+    Replace(usize, BigUint),
+    Bump(usize, BigInt),
+}
+
+impl<'a> FollowUp<'a> {
+    /// Parse `push`, `reroll {die}...`, `replace {die} {face}` or `bump {die} {amount}`, where dice are numbered from one.
+    fn parse(raw: &'a str) -> Option<Self> {
+        let raw = raw.trim();
+        let mut words = raw.split_whitespace();
+        let die = |word: Option<&str>| word?.parse::<usize>().ok().filter(|&die| die != 0);
+        let action = match words.next()? {
+            "push" => Action::Push,
+            "reroll" => Action::Reroll(
+                words
+                    .by_ref()
+                    .map(|word| die(Some(word)))
+                    .collect::<Option<_>>()?,
+            ),
+            "replace" => Action::Replace(die(words.next())?, words.next()?.parse().ok()?),
+            "bump" => Action::Bump(die(words.next())?, words.next()?.parse().ok()?),
+            _ => return None,
+        };
+        match words.next() {
+            Some(_) => None,
+            None => Some(Self { raw, action }),
+        }
+    }
+
+    fn apply<R: Rng>(&self, handle: &mut roll::RollHandle<R>) -> Result<(), roll::EvalError> {
+        match &self.action {
+            Action::Push => handle.push(),
+            Action::Reroll(dice) => {
+                handle.reroll(&dice.iter().map(|die| die - 1).collect::<Vec<_>>())
+            }
+            Action::Replace(die, face) => handle.replace(die - 1, face.clone()),
+            Action::Bump(die, amount) => handle.bump(die - 1, amount),
+        }
+        .map(|_| ())
+    }
+}
+
+/// Print a roll sheet line that couldn't be evaluated at all.
+fn fail(
+    raw: &str,
+    entry: Entry,
+    kind: &str,
+    error: &str,
+    format: Format,
+    mut output: impl io::Write,
+) -> io::Result<()> {
+    match format {
+        Format::Text => writeln!(io::stderr(), "Error on line {}: {error}", entry.number),
+        Format::Json => {
+            let error = Json::object([
+                ("stage", Json::from("sheet")),
+                ("kind", Json::from(kind)),
+                ("message", Json::from(error)),
+            ]);
+            let json = Json::object([("input", Json::from(raw)), ("error", error)]);
+            writeln!(output, "{}", annotate(json, Some(entry)))
+        }
+    }
+}
+
+/// Roll sheet line without anything following a `#` outside of quotes.
+fn uncommented(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
     }
-    Ok(())
+    line
 }
 
-fn cla(raw: &str, rng: &mut impl Rng) -> io::Result<()> {
-    eval(raw, rng, io::stdout())
+/// Split a roll sheet line into its label, if any, and its expression.
+///
+/// Text before the first `:` is only a label if it has no brackets or quotes, so that `:` can still be used within outcomes and maps.
+fn labeled(line: &str) -> (Option<&str>, &str) {
+    match line.split_once(':') {
+        Some((label, raw))
+            if !label.trim().is_empty() && !label.contains(['(', ')', '[', ']', '{', '}', '"']) =>
+        {
+            (Some(label.trim()), raw.trim())
+        }
+        _ => (None, line.trim()),
+    }
 }
 
-fn file(source: impl io::BufRead, rng: &mut impl Rng) -> io::Result<()> {
+/// Position of an expression within a roll sheet.
+#[derive(Clone, Copy)]
+struct Entry<'a> {
+    number: usize,
+    label: Option<&'a str>,
+}
+
+/// Print text with every inline expression replaced by its result.
+fn fill(
+    raw: &str,
+    variables: &Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    format: Format,
+    template: Template,
+) -> io::Result<Status> {
+    let style = match template {
+        Template::Values => roll::TemplateStyle::Values,
+        Template::Breakdowns => roll::TemplateStyle::Breakdowns,
+    };
+    let result = roll::eval_template_with(raw, variables, decks, style, rng);
+    let status = match &result {
+        Ok(_) => Status::Success,
+        Err(error) => Status::from(error),
+    };
     let mut output = io::stdout().lock();
-    for line in source.lines() {
-        let raw = line?;
-        eval(&raw, rng, &mut output)?;
+    match (result, format) {
+        (Ok(rendered), Format::Text) if rendered.ends_with('\n') => write!(output, "{rendered}")?,
+        (Ok(rendered), Format::Text) => writeln!(output, "{rendered}")?,
+        (Err(error), Format::Text) => writeln!(io::stderr(), "Error: {error}")?,
+        (Ok(rendered), Format::Json) => {
+            let json = Json::object([
+                ("input", Json::from(raw)),
+                ("output", Json::from(&*rendered)),
+            ]);
+            writeln!(output, "{json}")?;
+        }
+        (Err(error), Format::Json) => {
+            let json = Json::object([("input", Json::from(raw)), ("error", error.to_json())]);
+            writeln!(output, "{json}")?;
+        }
     }
-    Ok(())
+    Ok(status)
 }
 
-fn eval(raw: &str, rng: &mut impl Rng, mut output: impl io::Write) -> io::Result<()> {
-    match roll::eval(raw, rng) {
-        Ok(value) => writeln!(output, "{value}"),
-        Err(error) => writeln!(io::stderr(), "Error: {error}"),
+fn compare(
+    left: &str,
+    right: &str,
+    samples: NonZeroUsize,
+    variables: &Variables,
+    rng: &mut impl Rng,
+    format: Format,
+) -> io::Result<Status> {
+    let result = roll::compare(left, right, variables, samples, rng);
+    let status = match &result {
+        Ok(_) => Status::Success,
+        Err(error) => Status::from(error),
+    };
+    let mut output = io::stdout().lock();
+    let inputs = [("left", Json::from(left)), ("right", Json::from(right))];
+    match (result, format) {
+        (Ok(comparison), Format::Text) => writeln!(output, "{comparison}")?,
+        (Err(error), Format::Text) => writeln!(io::stderr(), "Error: {error}")?,
+        (Ok(comparison), Format::Json) => {
+            let Json::Object(fields) = comparison.to_json() else {
+                unreachable!("comparison should be an object");
+            };
+            let json = inputs.into_iter().map(|(key, value)| (key.into(), value));
+            writeln!(output, "{}", Json::Object(json.chain(fields).collect()))?;
+        }
+        (Err(error), Format::Json) => {
+            let [left, right] = inputs;
+            let json = Json::object([left, right, ("error", error.to_json())]);
+            writeln!(output, "{json}")?;
+        }
     }
+    Ok(status)
 }
 
+/// Evaluate and print an expression, returning its truthiness or the status of its failure.
+fn eval(
+    raw: &str,
+    entry: Option<Entry>,
+    variables: &mut Variables,
+    decks: &mut Decks,
+    rng: &mut impl Rng,
+    format: Format,
+    output: impl io::Write,
+) -> io::Result<Result<bool, Status>> {
+    let result = roll::evaluate_with_decks(raw, variables, decks, rng);
+    report(raw, result.as_ref(), entry, variables, format, output)
+}
+
+/// Print the result of an expression, returning its truthiness or the status of its failure.
+///
+/// The value is bound to the label of its entry if that label is a valid variable name.
+fn report(
+    raw: &str,
+    result: Result<&roll::Evaluation, &roll::EvalError>,
+    entry: Option<Entry>,
+    variables: &mut Variables,
+    format: Format,
+    mut output: impl io::Write,
+) -> io::Result<Result<bool, Status>> {
+    let outcome = match result {
+        Ok(evaluation) => Ok(evaluation.is_true()),
+        Err(error) => Err(Status::from(error)),
+    };
+    if let (
+        Ok(evaluation),
+        Some(Entry {
+            label: Some(label), ..
+        }),
+    ) = (result, entry)
+    {
+        if Variables::is_name(label) {
+            variables.insert(label, evaluation.value().clone());
+        }
+    }
+    match (result, format) {
+        (Ok(evaluation), Format::Text) => {
+            if let Some(Entry {
+                label: Some(label), ..
+            }) = entry
+            {
+                write!(output, "{label}: ")?;
+            }
+            writeln!(output, "{evaluation}")?;
+        }
+        (Err(error), Format::Text) => match entry {
+            Some(Entry { number, .. }) => {
+                writeln!(io::stderr(), "Error on line {number}: {error}")?
+            }
+            None => writeln!(io::stderr(), "Error: {error}")?,
+        },
+        (Ok(evaluation), Format::Json) => {
+            writeln!(output, "{}", annotate(evaluation.to_json(), entry))?;
+        }
+        (Err(error), Format::Json) => {
+            let json = Json::object([("input", Json::from(raw)), ("error", error.to_json())]);
+            writeln!(output, "{}", annotate(json, entry))?;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Prefix a JSON object with the line number and label of its roll sheet entry.
+fn annotate(json: Json, entry: Option<Entry>) -> Json {
+    let (Some(Entry { number, label }), Json::Object(fields)) = (entry, &json) else {
+        return json;
+    };
+    let mut annotated = vec![
+        ("line".into(), Json::from(number)),
+        ("label".into(), Json::from(label)),
+    ];
+    annotated.extend(fields.iter().cloned());
+    Json::Object(annotated)
+}
+
+/// Seed for the random number generator.
+///
+/// Parsed from 64 hexadecimal digits or, failing that, hashed from an arbitrary phrase.
+#[derive(Clone, Copy)]
 struct Seed([u8; 32]);
 
 impl FromArg for Seed {
     type Parent = Box<str>;
-    type Error = ();
+    type Error = Infallible;
 
     fn from_arg(arg: Self::Parent) -> Result<Self, Self::Error> {
-        let mut bytes: Box<[u8; 64]> = arg.into_boxed_bytes().try_into().map_err(|_| ())?;
-        for byte in bytes.iter_mut() {
-            *byte = match byte {
-                b'0'..=b'9' => *byte - b'0',
-                b'A'..=b'F' => *byte - b'A' + 10,
-                b'a'..=b'f' => *byte - b'a' + 10,
-                _ => return Err(()),
-            }
+        Ok(Self(hex(&arg).unwrap_or_else(|| hash(&arg))))
+    }
+
+    fn box_error(error: Self::Error) -> Box<dyn std::error::Error + Send + Sync> {
+        match error {}
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(seed) = self;
+
+        seed.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+fn hex(arg: &str) -> Option<[u8; 32]> {
+    let mut bytes: Box<[u8; 64]> = arg.as_bytes().to_vec().into_boxed_slice().try_into().ok()?;
+    for byte in bytes.iter_mut() {
+        *byte = match byte {
+            b'0'..=b'9' => *byte - b'0',
+            b'A'..=b'F' => *byte - b'A' + 10,
+            b'a'..=b'f' => *byte - b'a' + 10,
+            _ => return None,
         }
-        let seed: [u8; 32] = std::array::from_fn(|index| {
-            let high = bytes[2 * index];
-            let low = bytes[2 * index + 1];
-            16 * high + low
-        });
-        Ok(Self(seed))
     }
+    Some(std::array::from_fn(|index| {
+        let high = bytes[2 * index];
+        let low = bytes[2 * index + 1];
+        16 * high + low
+    }))
+}
+
+// FNV-1a is used since it's trivial to implement and stable across platforms and releases
+fn hash(phrase: &str) -> [u8; 32] {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
 
-    fn box_error((): Self::Error) -> Box<dyn std::error::Error + Send + Sync> {
-        Box::new(fcla::TypeError("seed"))
+    let mut seed = [0; 32];
+    // every lane is salted with its index so that they all differ
+    for (lane, chunk) in (0u8..).zip(seed.chunks_exact_mut(8)) {
+        let hash = std::iter::once(lane)
+            .chain(phrase.bytes())
+            .fold(OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+            });
+        chunk.copy_from_slice(&hash.to_le_bytes());
     }
+    seed
 }
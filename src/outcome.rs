@@ -0,0 +1,95 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use num::BigRational;
+use rand::Rng;
+
+/// Parse `=> {{band}: "{label}", ...}` following a value and label the value with the first band it falls in.
+///
+/// Bands are `{n}+` for `n` or more, `-{n}` for `n` or less, `{a}-{b}` for `a` to `b` and `{n}` for exactly `n`.
+/// Values that fall in no band are left unlabeled.
+pub fn main(state: &mut State<impl Rng>, value: Value) -> PartialResult {
+    if !state.lexer.matches(Token::EqualsGreaterThan) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    if !state.lexer.matches(Token::OpeningBrace) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push_str(" => {");
+    let mut label = None;
+    loop {
+        let band = band(state)?;
+        if !state.lexer.matches(Token::Colon) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        let text = state
+            .lexer
+            .matching(Token::Text)
+            .ok_or(EvalError::UnexpectedToken)?;
+        state.output.push_str(": ");
+        state.output.push_str(text);
+        if label.is_none() && band.contains(value.as_number()?) {
+            label = Some(text.trim_matches('"'));
+        }
+        if !state.lexer.matches(Token::Comma) {
+            break;
+        }
+        state.output.push_str(", ");
+    }
+    if !state.lexer.matches(Token::ClosingBrace) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push('}');
+    Ok(match label {
+        Some(label) => Value::Labeled(Box::new(value), label.into()),
+        None => value,
+    })
+}
+
+/// Range of values that a label applies to, where either bound may be absent.
+struct Band {
+    least: Option<BigRational>,
+    greatest: Option<BigRational>,
+}
+
+impl Band {
+    fn contains(&self, number: &BigRational) -> bool {
+        self.least.as_ref().is_none_or(|least| number >= least)
+            && self
+                .greatest
+                .as_ref()
+                .is_none_or(|greatest| number <= greatest)
+    }
+}
+
+fn band(state: &mut State<impl Rng>) -> Result<Band, EvalError> {
+    if state.lexer.matches(Token::Minus) {
+        state.output.push('-');
+        return Ok(Band {
+            least: None,
+            greatest: Some(bound(state)?),
+        });
+    }
+    let least = bound(state)?;
+    if state.lexer.matches(Token::Plus) {
+        state.output.push('+');
+        return Ok(Band {
+            least: Some(least),
+            greatest: None,
+        });
+    }
+    if state.lexer.matches(Token::Minus) {
+        state.output.push('-');
+        return Ok(Band {
+            least: Some(least),
+            greatest: Some(bound(state)?),
+        });
+    }
+    Ok(Band {
+        least: Some(least.clone()),
+        greatest: Some(least),
+    })
+}
+
+fn bound(state: &mut State<impl Rng>) -> Result<BigRational, EvalError> {
+    let value = super::number::main(state).ok_or(EvalError::UnexpectedToken)??;
+    Ok(value.into_number()?)
+}
@@ -0,0 +1,52 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use rand::Rng;
+use std::fmt;
+
+/// Parse `.{field}` following a value.
+pub fn field(state: &mut State<impl Rng>, value: Value) -> PartialResult {
+    if !state.lexer.matches(Token::Period) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    let name = state
+        .lexer
+        .matching(Token::Identifier)
+        .ok_or(EvalError::UnexpectedToken)?;
+    state.output.push('.');
+    state.output.push_str(name);
+    let field = value
+        .into_record()?
+        .into_vec()
+        .into_iter()
+        .find_map(|(field, value)| (*field == *name).then_some(value));
+    state.checked(
+        field.ok_or_else(|| RecordError::UndefinedField(name.into())),
+        Value::zero,
+    )
+}
+
+#[derive(Debug)]
+pub enum RecordError {
+    UndefinedField(Box<str>),
+}
+
+impl RecordError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UndefinedField(_) => "undefined_field",
+        }
+    }
+}
+
+impl From<RecordError> for EvalError {
+    fn from(error: RecordError) -> Self {
+        Self::Record(error)
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedField(name) => write!(f, "undefined field `{name}`"),
+        }
+    }
+}
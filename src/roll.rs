@@ -1,28 +1,393 @@
+mod json;
 mod parse;
 mod realize;
 mod value;
 
-use super::Value;
+use super::{json::Json, Token, Value};
 use parse::ParseError;
 use rand::Rng;
-use realize::RealizeError;
+use realize::{Realize, RealizeError};
 use std::fmt::{self, Write as _};
 
-pub fn main(state: &mut super::State<impl Rng>) -> Option<Result<Value, RollError>> {
-    let raw = state.lexer.matching(super::Token::Roll)?;
-    Some(inner(raw, &mut state.output, state.rng))
+pub use realize::Die;
+
+/// Dice of every plain roll of an expression, which are used in place of rolling them again when it's evaluated again.
+pub type Replay = std::vec::IntoIter<Vec<Die>>;
+
+/// Parse a roll such as `4d6dl1`, where every argument is a literal, variable or grouping.
+///
+/// Rolls may be chained, in which case `1d4d6` is the same as `(1d4)d6`.
+pub fn main(state: &mut super::State<impl Rng>) -> Result<Value, super::EvalError> {
+    // only the outermost dice are doubled, not those rolled for their number, size or arguments
+    let doubling = std::mem::replace(&mut state.doubling, false);
+    let result = chain(state, doubling);
+    state.doubling = doubling;
+    result
+}
+
+fn chain(state: &mut super::State<impl Rng>, doubling: bool) -> Result<Value, super::EvalError> {
+    let start = state.output.len();
+    let mut roll_len = if state.lexer.peek(Token::D) {
+        None
+    } else {
+        Some(parse::operand(state)?)
+    };
+    while state.lexer.matches(Token::D) {
+        let roll_len_text = state.output.split_off(start);
+        let (mut parse, spec) = parse::main(state, roll_len.take(), roll_len_text)?;
+        if state.skipping {
+            roll_len = Some(parse::Operand {
+                value: Value::zero(),
+                literal: false,
+            });
+            continue;
+        }
+        if doubling && !state.lexer.peek(Token::D) {
+            parse.roll_len *= 2u32;
+        }
+        // dice that no longer fit the roll, such as when the number of dice was itself rerolled, are rolled afresh
+        let replayed = state
+            .replay
+            .as_mut()
+            .and_then(Iterator::next)
+            .filter(|dice| {
+                num::BigUint::from(dice.len()) == parse.roll_len
+                    && dice.iter().all(|die| die.rolled <= parse.die_size)
+            });
+        let realize = match replayed {
+            Some(dice) => realize::partition(&parse, dice),
+            None => realize::main(&parse, state.rng),
+        }
+        .map_err(RollError::from)?;
+        if spec.computed {
+            state.output.push_str(&spec.text);
+        }
+        write!(state.output, "{realize}").map_err(RollError::from)?;
+        let value = value::main(&realize);
+        // critical ranges apply to the face a die came up on, before any modifiers
+        let within =
+            |range: &parse::Range| realize.kept().iter().any(|die| range.contains(&die.rolled));
+        let pool = Pool {
+            critical: parse.critical.as_ref().map(within),
+            fumble: parse.fumble.as_ref().map(within),
+            realize,
+            parse: Some(parse),
+        };
+        if pool.critical == Some(true) {
+            state.output.push_str(" (critical)");
+        }
+        if pool.fumble == Some(true) {
+            state.output.push_str(" (fumble)");
+        }
+        state.pools.push(pool);
+        roll_len = Some(parse::Operand {
+            value,
+            literal: false,
+        });
+    }
+    let roll_len = roll_len.expect("either an operand or a roll should have been parsed");
+    Ok(roll_len.value)
+}
+
+/// Parse and roll the dice of a Cortex Prime pool, which are displayed in place of their source.
+///
+/// The value is a record of the `total`, the size of the `effect` die and the number of `hitches`.
+pub fn cortex(state: &mut super::State<impl Rng>) -> Result<Value, super::EvalError> {
+    let start = state.output.len();
+    let sizes = parse::cortex(state)?;
+    if state.skipping {
+        return Ok(Value::record([
+            ("total", Value::zero()),
+            ("effect", Value::zero()),
+            ("hitches", Value::zero()),
+        ]));
+    }
+    let realize = Realize::Cortex(realize::cortex(sizes, state.rng).map_err(RollError::from)?);
+    state.output.truncate(start);
+    write!(state.output, "{realize}").map_err(RollError::from)?;
+    let value = value::main(&realize);
+    state.pools.push(Pool::from(realize));
+    Ok(value)
+}
+
+/// Parse and roll a Savage Worlds trait roll such as `d8, 4, 2` with its optional target number and modifier.
+///
+/// The value is a record of the `total`, whether it's a `success`, the number of `raises` and whether it's a `critical_failure`.
+pub fn savage(state: &mut super::State<impl Rng>) -> Result<Value, super::EvalError> {
+    use num::One as _;
+
+    let start = state.output.len();
+    let trait_size = parse::die(state)?;
+    let realize = match trait_size {
+        Some(trait_size) => {
+            let realize = realize::savage(trait_size, state.rng).map_err(RollError::from)?;
+            state.output.truncate(start);
+            write!(state.output, "{realize}").map_err(RollError::from)?;
+            Some(realize)
+        }
+        None => None,
+    };
+    let mut target = Value::from(4);
+    let mut modifier = Value::zero();
+    for argument in [&mut target, &mut modifier] {
+        if !state.lexer.matches(Token::Comma) {
+            break;
+        }
+        state.output.push_str(", ");
+        *argument = super::expression(state)?;
+    }
+    let Some(realize) = realize else {
+        target.into_number()?;
+        modifier.into_number()?;
+        return Ok(Value::record([
+            ("total", Value::zero()),
+            ("success", Value::Bool(false)),
+            ("raises", Value::zero()),
+            ("critical_failure", Value::Bool(false)),
+        ]));
+    };
+    // snake eyes are a critical failure regardless of aces
+    let critical_failure =
+        realize.trait_die.faces[0].is_one() && realize.wild_die.faces[0].is_one();
+    let realize = Realize::Savage(realize);
+    let value = value::main(&realize);
+    state.pools.push(Pool::from(realize));
+    let total = value.add(modifier)?.into_number()?;
+    let target = target.into_number()?;
+    let success = total >= target;
+    let raises = crate::versus::raises(&(&total - target));
+    Ok(Value::record([
+        ("total", Value::from(total)),
+        ("success", Value::Bool(success)),
+        ("raises", Value::from(raises)),
+        ("critical_failure", Value::Bool(critical_failure)),
+    ]))
+}
+
+/// Parse and roll a pool of narrative dice, which are displayed in place of their source.
+///
+/// The value is the net symbols of every die.
+pub fn narrative(state: &mut super::State<impl Rng>) -> Result<Value, super::EvalError> {
+    let start = state.output.len();
+    let kinds = parse::narrative(state)?;
+    if state.skipping {
+        return Ok(Value::Symbols(Default::default()));
+    }
+    let realize = Realize::Narrative(realize::narrative(kinds, state.rng));
+    state.output.truncate(start);
+    write!(state.output, "{realize}").map_err(RollError::from)?;
+    let value = value::main(&realize);
+    state.pools.push(Pool::from(realize));
+    Ok(value)
+}
+
+/// Roll the exploding dice that a step translates to and add its modifier.
+pub fn step(
+    state: &mut super::State<impl Rng>,
+    dice: &crate::step::Dice,
+) -> Result<Value, super::EvalError> {
+    let realize = Realize::Step(realize::step(&dice.sizes, state.rng).map_err(RollError::from)?);
+    write!(state.output, "{realize}").map_err(RollError::from)?;
+    let value = value::main(&realize);
+    state.pools.push(Pool::from(realize));
+    Ok(value.add(Value::from(dice.modifier.clone()))?)
+}
+
+/// Roll some number of dice of the given size without any arguments, returning the faces they roll.
+pub fn plain(
+    state: &mut super::State<impl Rng>,
+    roll_len: Value,
+    die_size: num::BigUint,
+) -> Result<Vec<num::BigUint>, super::EvalError> {
+    let parse = parse::Parse {
+        roll_len: parse::natural(&roll_len, parse::Argument::RollLen)?,
+        die_size,
+        drop_least: None,
+        drop_greatest: None,
+        count_greater: None,
+        count_lesser: None,
+        critical: None,
+        fumble: None,
+        modifiers: parse::Modifiers::default(),
+        unique: false,
+    };
+    let realize = realize::main(&parse, state.rng).map_err(RollError::from)?;
+    let faces = realize.kept().iter().map(|die| die.face.clone()).collect();
+    write!(state.output, "{realize}").map_err(RollError::from)?;
+    state.pools.push(Pool::from(realize));
+    Ok(faces)
+}
+
+/// Parse and roll a Blades in the Dark action roll of some number of d6, labeled with its outcome.
+///
+/// The highest die is taken, or the lowest of two when rolling no dice, and more than one six is a critical.
+pub fn blades(state: &mut super::State<impl Rng>) -> Result<Value, super::EvalError> {
+    use num::{BigUint, One as _, Zero as _};
+
+    let roll_len = super::expression(state)?;
+    let roll_len = state.checked(
+        parse::natural(&roll_len, parse::Argument::RollLen),
+        BigUint::zero,
+    )?;
+    if state.skipping {
+        return Ok(Value::zero());
+    }
+    let desperate = roll_len.is_zero();
+    let parse = if desperate {
+        parse::Parse {
+            roll_len: BigUint::from(2u32),
+            die_size: BigUint::from(6u32),
+            drop_least: None,
+            drop_greatest: Some(BigUint::one()),
+            count_greater: None,
+            count_lesser: None,
+            critical: None,
+            fumble: None,
+            modifiers: parse::Modifiers::default(),
+            unique: false,
+        }
+    } else {
+        parse::Parse {
+            drop_least: Some(&roll_len - 1u32).filter(|drop| !drop.is_zero()),
+            roll_len,
+            die_size: BigUint::from(6u32),
+            drop_greatest: None,
+            count_greater: None,
+            count_lesser: None,
+            critical: None,
+            fumble: None,
+            modifiers: parse::Modifiers::default(),
+            unique: false,
+        }
+    };
+    let realize = realize::main(&parse, state.rng).map_err(RollError::from)?;
+    let Realize::Under(under) = &realize else {
+        unreachable!("fewer dice should be dropped than are rolled");
+    };
+    let six = BigUint::from(6u32);
+    let sixes = under.faces().filter(|die| die.face == six).count();
+    let value = value::main(&realize);
+    let label = match value.to_natural().expect("roll should be natural") {
+        _ if sixes > 1 && !desperate => "critical",
+        face if face == six => "full success",
+        face if face >= BigUint::from(4u32) => "partial success",
+        _ => "failure",
+    };
+    write!(state.output, " {realize}").map_err(RollError::from)?;
+    state.pools.push(Pool::from(realize));
+    Ok(Value::Labeled(Box::new(value), label.into()))
+}
+
+/// Realized roll kept around for structured output.
+#[derive(Debug)]
+pub struct Pool {
+    realize: Realize,
+    /// Whether a kept die is within the critical range, if the roll has one.
+    critical: Option<bool>,
+    /// Whether a kept die is within the fumble range, if the roll has one.
+    fumble: Option<bool>,
+    /// Arguments of a plain roll, which its dice can be rolled again with.
+    parse: Option<parse::Parse>,
+}
+
+impl From<Realize> for Pool {
+    fn from(realize: Realize) -> Self {
+        Self {
+            realize,
+            critical: None,
+            fumble: None,
+            parse: None,
+        }
+    }
 }
 
-fn inner(raw: &str, output: &mut String, rng: &mut impl Rng) -> Result<Value, RollError> {
-    let parse = parse::main(raw)?;
-    let realize = realize::main(parse, rng)?;
-    write!(output, "{realize}")?;
-    Ok(value::main(realize))
+impl Pool {
+    /// Size and face of the only die kept by the roll, if it kept exactly one.
+    pub fn natural(&self) -> Option<(&num::BigUint, &num::BigUint)> {
+        self.realize.natural()
+    }
+
+    /// Dice that make up the value of the roll, or those that are counted.
+    pub fn kept(&self) -> &[realize::Die] {
+        self.realize.kept()
+    }
+
+    /// Value of the dice alone, without anything added to them.
+    pub fn value(&self) -> Value {
+        value::main(&self.realize)
+    }
+
+    /// Dice of a plain roll in the order they're displayed, or nothing for any other kind of roll.
+    pub fn dice(&self) -> Option<Vec<Die>> {
+        self.parse.as_ref()?;
+        Some(self.realize.dice().into_iter().cloned().collect())
+    }
+
+    /// Roll one of the dice of a plain roll again, where `dice` are those of the roll in the order they're displayed.
+    pub fn reroll(&self, dice: &[Die], index: usize, rng: &mut impl Rng) -> Die {
+        let taken = others(dice, index)
+            .map(|die| &die.rolled)
+            .collect::<Vec<_>>();
+        realize::reroll(self.plain(), &taken, rng)
+    }
+
+    /// Die of a plain roll that came up on the given face.
+    pub fn replace(&self, face: num::BigUint) -> Die {
+        realize::replace(self.plain(), face)
+    }
+
+    /// Whether one of the dice of a plain roll could come up on a face, which must be on the die and not already shown by another die of a roll without replacement.
+    pub fn can_show(&self, dice: &[Die], index: usize, face: &num::BigUint) -> bool {
+        use num::Zero as _;
+
+        let parse = self.plain();
+        !face.is_zero()
+            && *face <= parse.die_size
+            && !(parse.unique && others(dice, index).any(|die| die.rolled == *face))
+    }
+
+    /// Whether a die of a plain roll is neither a success, showing the greatest face, nor a bane, showing a one.
+    pub fn is_pushable(&self, die: &Die) -> bool {
+        use num::One as _;
+
+        !die.face.is_one() && die.face < self.plain().die_size
+    }
+
+    fn plain(&self) -> &parse::Parse {
+        self.parse
+            .as_ref()
+            .expect("only the dice of plain rolls should be changed")
+    }
+
+    pub fn to_json(&self) -> Json {
+        let Self {
+            realize,
+            critical,
+            fumble,
+            ..
+        } = self;
+
+        let mut json = json::main(realize);
+        if let Json::Object(fields) = &mut json {
+            fields.push(("critical".into(), Json::from(*critical)));
+            fields.push(("fumble".into(), Json::from(*fumble)));
+        }
+        json
+    }
+}
+
+/// Every die other than the one at `index`.
+fn others(dice: &[Die], index: usize) -> impl Iterator<Item = &Die> {
+    dice.iter()
+        .enumerate()
+        .filter(move |&(other, _)| other != index)
+        .map(|(_, die)| die)
 }
 
 #[derive(Debug)]
 pub enum RollError {
     Parse(ParseError),
+    Argument(parse::ArgumentError),
     Realize(RealizeError),
 }
 
@@ -54,7 +419,26 @@ impl fmt::Display for RollError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Parse(error) => write!(f, "parsing roll: {error}"),
+            Self::Argument(error) => write!(f, "evaluating roll: {error}"),
             Self::Realize(error) => write!(f, "generating roll: {error}"),
         }
     }
 }
+
+impl RollError {
+    pub fn class(&self) -> crate::ErrorClass {
+        match self {
+            Self::Parse(_) => crate::ErrorClass::Syntax,
+            Self::Argument(_) => crate::ErrorClass::Evaluation,
+            Self::Realize(error) => error.class(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Parse(error) => error.kind(),
+            Self::Argument(error) => error.kind(),
+            Self::Realize(error) => error.kind(),
+        }
+    }
+}
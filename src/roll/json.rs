@@ -0,0 +1,203 @@
+use super::realize::{self, Realize};
+use crate::json::Json;
+use num::BigUint;
+
+pub fn main(realize: &Realize) -> Json {
+    // the faces of narrative dice are symbols rather than numbers
+    if let Realize::Narrative(inner) = realize {
+        let kept = inner
+            .dice
+            .iter()
+            .map(|die| {
+                Json::object([
+                    ("die", Json::from(die.kind.to_string())),
+                    ("symbols", die.symbol_names().map(Json::from).collect()),
+                ])
+            })
+            .collect();
+        return Json::object([
+            ("output", Json::from(realize.to_string())),
+            ("kept", kept),
+            ("dropped", Json::Array(Vec::new())),
+            ("counted", Json::Null),
+        ]);
+    }
+    let Partition {
+        kept,
+        dropped,
+        counted,
+    } = realize.partition();
+    let mut json = Json::object([
+        ("output", Json::from(realize.to_string())),
+        ("kept", kept.into_iter().collect()),
+        ("dropped", dropped.into_iter().collect()),
+        ("counted", Json::from(counted.map(Json::from_iter))),
+    ]);
+    if let (Realize::Cortex(inner), Json::Object(fields)) = (realize, &mut json) {
+        let sizes = |role| {
+            inner
+                .dice
+                .iter()
+                .filter(move |die| die.role == role)
+                .map(|die| &die.size)
+        };
+        let effect = sizes(realize::CortexRole::Effect).next();
+        fields.push(("effect".into(), effect.map_or(Json::Null, Json::from)));
+        fields.push((
+            "hitches".into(),
+            sizes(realize::CortexRole::Hitch).collect(),
+        ));
+    }
+    json
+}
+
+#[derive(Default)]
+struct Partition<'a> {
+    kept: Vec<&'a BigUint>,
+    dropped: Vec<&'a BigUint>,
+    counted: Option<Vec<&'a BigUint>>,
+}
+
+trait Partitioned {
+    fn partition(&self) -> Partition<'_>;
+}
+
+impl Partitioned for Realize {
+    fn partition(&self) -> Partition<'_> {
+        match self {
+            Self::Under(inner) => inner.partition(),
+            Self::OverDropped(inner) => inner.partition(),
+            Self::OverFiltered(inner) => inner.partition(),
+            Self::Cortex(inner) => inner.partition(),
+            Self::Savage(inner) => inner.partition(),
+            Self::Step(inner) => Partition {
+                kept: inner.dice.iter().flat_map(|die| die.faces.iter()).collect(),
+                ..Partition::default()
+            },
+            Self::Narrative(_) => Partition::default(),
+        }
+    }
+}
+
+impl Partitioned for realize::RealizeUnder {
+    fn partition(&self) -> Partition<'_> {
+        let Self {
+            least,
+            lesser,
+            middle,
+            greater,
+            greatest,
+            ..
+        } = self;
+
+        let dropped = chain([least, greatest]);
+        if lesser.is_some() || greater.is_some() {
+            let kept = lesser
+                .iter()
+                .chain([middle])
+                .chain(greater)
+                .flat_map(|dice| faces(dice))
+                .collect();
+            Partition {
+                kept,
+                dropped,
+                counted: Some(faces(middle).collect()),
+            }
+        } else {
+            Partition {
+                kept: faces(middle).collect(),
+                dropped,
+                counted: None,
+            }
+        }
+    }
+}
+
+impl Partitioned for realize::RealizeOverDropped {
+    fn partition(&self) -> Partition<'_> {
+        let Self {
+            least,
+            middle,
+            greatest,
+        } = self;
+
+        let mut dropped = chain([least, greatest]);
+        dropped.extend(faces(middle));
+        Partition {
+            dropped,
+            ..Partition::default()
+        }
+    }
+}
+
+impl Partitioned for realize::RealizeOverFiltered {
+    fn partition(&self) -> Partition<'_> {
+        let Self {
+            least,
+            lesser,
+            middle,
+            greater,
+            greatest,
+        } = self;
+
+        let kept = [lesser, middle, greater]
+            .into_iter()
+            .flat_map(|dice| faces(dice))
+            .collect();
+        Partition {
+            kept,
+            dropped: chain([least, greatest]),
+            counted: Some(Vec::new()),
+        }
+    }
+}
+
+impl Partitioned for realize::RealizeCortex {
+    fn partition(&self) -> Partition<'_> {
+        let Self { dice } = self;
+
+        // the effect die and hitches have their own fields
+        let faces = |role| {
+            dice.iter()
+                .filter(move |die| die.role == role)
+                .map(|die| &die.face)
+                .collect()
+        };
+        Partition {
+            kept: faces(realize::CortexRole::Total),
+            dropped: faces(realize::CortexRole::Unused),
+            counted: None,
+        }
+    }
+}
+
+impl Partitioned for realize::RealizeSavage {
+    fn partition(&self) -> Partition<'_> {
+        let Self {
+            trait_die,
+            wild_die,
+        } = self;
+
+        let (kept, dropped) = if self.wild_is_kept() {
+            (wild_die, trait_die)
+        } else {
+            (trait_die, wild_die)
+        };
+        Partition {
+            kept: kept.faces.iter().collect(),
+            dropped: dropped.faces.iter().collect(),
+            counted: None,
+        }
+    }
+}
+
+fn chain<const LEN: usize>(dice: [&Option<Box<[realize::Die]>>; LEN]) -> Vec<&BigUint> {
+    dice.into_iter()
+        .flatten()
+        .flat_map(|dice| faces(dice))
+        .collect()
+}
+
+fn faces(dice: &[realize::Die]) -> impl Iterator<Item = &BigUint> {
+    dice.iter().map(|die| &die.face)
+}
@@ -1,8 +1,11 @@
-use crate::utils::parse_validated;
-use num::BigUint;
+use super::super::{State, Token, Value};
+use super::realize::{Narrative, RealizeError};
+use crate::{value::NaturalError, EvalError};
+use num::{BigInt, BigUint, Zero as _};
+use rand::Rng;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Parse {
     pub roll_len: BigUint,
     pub die_size: BigUint,
@@ -10,89 +13,484 @@ pub struct Parse {
     pub drop_greatest: Option<BigUint>,
     pub count_greater: Option<BigUint>,
     pub count_lesser: Option<BigUint>,
+    pub critical: Option<Range>,
+    pub fumble: Option<Range>,
+    pub modifiers: Modifiers,
+    /// Whether no two dice may roll the same face, as if drawn without replacement.
+    pub unique: bool,
+}
+
+/// Changes made to every die of a roll before it's partitioned, such as `each+1` or `min10`.
+#[derive(Debug, Clone, Default)]
+pub struct Modifiers {
+    /// Faces that become other faces, such as `map{1: 6}`.
+    pub map: Box<[(BigUint, BigUint)]>,
+    pub each: Option<BigInt>,
+    pub minimum: Option<BigUint>,
+    pub maximum: Option<BigUint>,
+}
+
+/// Faces that a kept die must roll for a roll to be a critical or a fumble, such as `>=19`.
+#[derive(Debug, Clone)]
+pub struct Range {
+    pub comparison: Comparison,
+    pub threshold: BigUint,
+}
+
+impl Range {
+    pub fn contains(&self, face: &BigUint) -> bool {
+        let Self {
+            comparison,
+            threshold,
+        } = self;
+
+        match comparison {
+            Comparison::Lesser => face < threshold,
+            Comparison::LesserOrEqual => face <= threshold,
+            Comparison::Equal => face == threshold,
+            Comparison::GreaterOrEqual => face >= threshold,
+            Comparison::Greater => face > threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Comparison {
+    Lesser,
+    LesserOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// Source of a roll as it should be displayed.
+#[derive(Debug)]
+pub struct Spec {
+    pub text: String,
+    /// Whether any argument is something other than a literal.
+    pub computed: bool,
+}
+
+/// Argument to a roll which has already been evaluated.
+#[derive(Debug)]
+pub struct Operand {
+    pub value: Value,
+    pub literal: bool,
 }
 
 #[derive(Debug)]
 pub enum ParseError {
-    InvalidArgumentKey,
     RepeatedArgumentKey,
-    EmptyArgumentValue,
+    UndefinedDie(Box<str>),
+}
+
+/// Argument whose value can't be used, which is only known once it's been evaluated.
+#[derive(Debug)]
+pub enum ArgumentError {
+    Negative(Argument),
+    Fractional(Argument),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Argument {
+    RollLen,
+    DieSize,
+    DropLeast,
+    DropGreatest,
+    CountGreater,
+    CountLesser,
+    Critical,
+    Fumble,
+    Each,
+    Minimum,
+    Maximum,
+    Map,
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::InvalidArgumentKey => write!(f, "invalid argument key"),
             Self::RepeatedArgumentKey => write!(f, "repeated argument key"),
-            Self::EmptyArgumentValue => write!(f, "empty argument value"),
+            Self::UndefinedDie(name) => write!(f, "undefined die `{name}`"),
+        }
+    }
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Negative(argument) => write!(f, "{argument} is negative"),
+            Self::Fractional(argument) => write!(f, "{argument} is fractional"),
+        }
+    }
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RollLen => write!(f, "number of dice"),
+            Self::DieSize => write!(f, "die size"),
+            Self::DropLeast => write!(f, "number of least rolls to drop"),
+            Self::DropGreatest => write!(f, "number of greatest rolls to drop"),
+            Self::CountGreater => write!(f, "count greater threshold"),
+            Self::CountLesser => write!(f, "count lesser threshold"),
+            Self::Critical => write!(f, "critical threshold"),
+            Self::Fumble => write!(f, "fumble threshold"),
+            Self::Each => write!(f, "per-die modifier"),
+            Self::Minimum => write!(f, "minimum face"),
+            Self::Maximum => write!(f, "maximum face"),
+            Self::Map => write!(f, "mapped face"),
         }
     }
 }
 
-pub fn main(raw: &str) -> Result<Parse, ParseError> {
+impl ParseError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RepeatedArgumentKey => "repeated_argument_key",
+            Self::UndefinedDie(_) => "undefined_die",
+        }
+    }
+}
+
+impl ArgumentError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Negative(_) => "negative_argument",
+            Self::Fractional(_) => "fractional_argument",
+        }
+    }
+}
+
+/// Parse the rest of a roll after its `d`.
+pub fn main(
+    state: &mut State<impl Rng>,
+    roll_len: Option<Operand>,
+    roll_len_text: String,
+) -> Result<(Parse, Spec), EvalError> {
     use num::One as _;
     use std::cell::OnceCell;
 
-    let (roll_len, rest) = raw
-        .split_once('d')
-        .expect("lexer should validate existence");
-    let (die_size, mut rest) = split_once_number(rest);
+    let mut spec = Spec {
+        text: roll_len_text,
+        computed: roll_len.as_ref().is_some_and(|operand| !operand.literal),
+    };
+    let roll_len = match roll_len {
+        Some(operand) => {
+            state.checked(natural(&operand.value, Argument::RollLen), BigUint::zero)?
+        }
+        None => BigUint::one(),
+    };
+    spec.text.push('d');
+    let die_size = argument(state, &mut spec, Argument::DieSize)?;
     let drop_least = OnceCell::new();
     let drop_greatest = OnceCell::new();
     let count_greater = OnceCell::new();
     let count_lesser = OnceCell::new();
     let map = [
-        ("dl", &drop_least),
-        ("dg", &drop_greatest),
-        ("cg", &count_greater),
-        ("cl", &count_lesser),
+        (Token::DropLeast, "dl", Argument::DropLeast, &drop_least),
+        (
+            Token::DropGreatest,
+            "dg",
+            Argument::DropGreatest,
+            &drop_greatest,
+        ),
+        (
+            Token::CountGreater,
+            "cg",
+            Argument::CountGreater,
+            &count_greater,
+        ),
+        (
+            Token::CountLesser,
+            "cl",
+            Argument::CountLesser,
+            &count_lesser,
+        ),
     ];
+    let critical = OnceCell::new();
+    let fumble = OnceCell::new();
+    let ranges = [
+        (Token::CriticalSuccess, "cs", Argument::Critical, &critical),
+        (Token::CriticalFailure, "cf", Argument::Fumble, &fumble),
+    ];
+    // these are words rather than keywords so that `min` and `max` remain functions elsewhere
+    let minimum = OnceCell::new();
+    let maximum = OnceCell::new();
+    let clamps = [
+        ("min", Argument::Minimum, &minimum),
+        ("max", Argument::Maximum, &maximum),
+    ];
+    let each = OnceCell::new();
+    let face_map = OnceCell::new();
+    let mut unique = false;
     'outer: loop {
-        if rest.is_empty() {
-            break;
+        for (token, key, kind, value) in map {
+            if state.lexer.matches(token) {
+                spec.text.push_str(key);
+                let arg = argument(state, &mut spec, kind)?;
+                if value.set(arg).is_err() {
+                    return Err(ParseError::RepeatedArgumentKey.into());
+                }
+                continue 'outer;
+            }
         }
-        for (key, value) in map {
-            if let Some(suffix) = rest.strip_prefix(key) {
-                let (arg, tail) = split_once_number(suffix);
+        for (token, key, kind, value) in ranges {
+            if state.lexer.matches(token) {
+                spec.text.push_str(key);
+                let arg = range(state, &mut spec, kind)?;
                 if value.set(arg).is_err() {
-                    return Err(ParseError::RepeatedArgumentKey);
+                    return Err(ParseError::RepeatedArgumentKey.into());
                 }
-                rest = tail;
                 continue 'outer;
             }
         }
-        return Err(ParseError::InvalidArgumentKey);
+        for (word, kind, value) in clamps {
+            if state.lexer.matches_word(word) {
+                spec.text.push_str(word);
+                let arg = argument(state, &mut spec, kind)?;
+                if value.set(arg).is_err() {
+                    return Err(ParseError::RepeatedArgumentKey.into());
+                }
+                continue 'outer;
+            }
+        }
+        if state.lexer.matches_word("each") {
+            spec.text.push_str("each");
+            let arg = signed(state, &mut spec, Argument::Each)?;
+            if each.set(arg).is_err() {
+                return Err(ParseError::RepeatedArgumentKey.into());
+            }
+            continue;
+        }
+        if let Some(word) = ["u", "nodup"]
+            .into_iter()
+            .find(|word| state.lexer.matches_word(word))
+        {
+            spec.text.push_str(word);
+            if std::mem::replace(&mut unique, true) {
+                return Err(ParseError::RepeatedArgumentKey.into());
+            }
+            continue;
+        }
+        if state.lexer.matches_word("map") {
+            spec.text.push_str("map");
+            let arg = face_map_argument(state, &mut spec)?;
+            if face_map.set(arg).is_err() {
+                return Err(ParseError::RepeatedArgumentKey.into());
+            }
+            continue;
+        }
+        break;
     }
+    let parse = Parse {
+        roll_len,
+        die_size,
+        drop_least: drop_least.into_inner(),
+        drop_greatest: drop_greatest.into_inner(),
+        count_greater: count_greater.into_inner(),
+        count_lesser: count_lesser.into_inner(),
+        critical: critical.into_inner(),
+        fumble: fumble.into_inner(),
+        modifiers: Modifiers {
+            map: face_map.into_inner().unwrap_or_default(),
+            each: each.into_inner(),
+            minimum: minimum.into_inner(),
+            maximum: maximum.into_inner(),
+        },
+        unique,
+    };
+    Ok((parse, spec))
+}
 
-    macro_rules! parse_arg {
-        ($arg:ident) => {
-            match $arg.into_inner() {
-                Some("") => return Err(ParseError::EmptyArgumentValue),
-                Some(non_empty) => Some(parse_validated(non_empty)),
-                None => None,
+/// Parse the comma-separated dice of a Cortex Prime pool such as `d8, 2d6, d10` into their sizes.
+pub fn cortex(state: &mut State<impl Rng>) -> Result<Vec<BigUint>, EvalError> {
+    let mut sizes = Vec::new();
+    loop {
+        let roll_len = if state.lexer.peek(Token::D) {
+            None
+        } else {
+            Some(operand(state)?)
+        };
+        if !state.lexer.matches(Token::D) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        state.output.push('d');
+        let die_size = operand(state)?;
+        let roll_len = match roll_len {
+            Some(operand) => {
+                state.checked(natural(&operand.value, Argument::RollLen), BigUint::zero)?
             }
+            None => BigUint::from(1u32),
         };
+        let die_size = state.checked(natural(&die_size.value, Argument::DieSize), BigUint::zero)?;
+        if !state.skipping {
+            let roll_len = usize::try_from(roll_len)
+                .map_err(|_| super::RollError::from(RealizeError::RollLenExceedsMaximum))?;
+            sizes.extend(std::iter::repeat_n(die_size, roll_len));
+        }
+        if !state.lexer.matches(Token::Comma) {
+            return Ok(sizes);
+        }
+        state.output.push_str(", ");
+    }
+}
+
+/// Parse the comma-separated dice of a narrative pool such as `2 ability, proficiency, 3 difficulty` into their kinds.
+///
+/// Counts are written before the name of a die and must be literals or groupings.
+pub fn narrative(state: &mut State<impl Rng>) -> Result<Vec<Narrative>, EvalError> {
+    let mut kinds = Vec::new();
+    loop {
+        let roll_len = if state.lexer.peek(Token::Identifier) {
+            None
+        } else {
+            let roll_len = operand(state)?;
+            state.output.push(' ');
+            Some(roll_len)
+        };
+        let name = state
+            .lexer
+            .matching(Token::Identifier)
+            .ok_or(EvalError::UnexpectedToken)?;
+        state.output.push_str(name);
+        let kind =
+            Narrative::from_name(name).ok_or_else(|| ParseError::UndefinedDie(name.into()))?;
+        let roll_len = match roll_len {
+            Some(operand) => {
+                state.checked(natural(&operand.value, Argument::RollLen), BigUint::zero)?
+            }
+            None => BigUint::from(1u32),
+        };
+        if !state.skipping {
+            let roll_len = usize::try_from(roll_len)
+                .map_err(|_| super::RollError::from(RealizeError::RollLenExceedsMaximum))?;
+            kinds.extend(std::iter::repeat_n(kind, roll_len));
+        }
+        if !state.lexer.matches(Token::Comma) {
+            return Ok(kinds);
+        }
+        state.output.push_str(", ");
     }
+}
+
+/// Parse a single die such as `d8` into its size, which is absent when skipping.
+pub fn die(state: &mut State<impl Rng>) -> Result<Option<BigUint>, EvalError> {
+    if !state.lexer.matches(Token::D) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    state.output.push('d');
+    let die_size = operand(state)?;
+    let die_size = state.checked(natural(&die_size.value, Argument::DieSize), BigUint::zero)?;
+    Ok((!state.skipping).then_some(die_size))
+}
+
+/// Parse an argument made up of a single literal, variable or grouping.
+pub fn operand(state: &mut State<impl Rng>) -> Result<Operand, EvalError> {
+    let literal = state.lexer.peek(Token::Number);
+    let value = super::super::primary(state)?;
+    Ok(Operand { value, literal })
+}
+
+fn argument(
+    state: &mut State<impl Rng>,
+    spec: &mut Spec,
+    kind: Argument,
+) -> Result<BigUint, EvalError> {
+    let start = state.output.len();
+    let Operand { value, literal } = operand(state)?;
+    spec.text.extend(state.output.drain(start..));
+    spec.computed |= !literal;
+    state.checked(natural(&value, kind), BigUint::zero)
+}
 
-    let roll_len = if !roll_len.is_empty() {
-        parse_validated(roll_len)
+/// Parse a threshold optionally preceded by a comparison, which defaults to equality.
+fn range(state: &mut State<impl Rng>, spec: &mut Spec, kind: Argument) -> Result<Range, EvalError> {
+    let comparisons = [
+        (Token::LessThan, "<", Comparison::Lesser),
+        (Token::LessThanEquals, "<=", Comparison::LesserOrEqual),
+        (Token::Equals, "=", Comparison::Equal),
+        (Token::GreaterThanEquals, ">=", Comparison::GreaterOrEqual),
+        (Token::GreaterThan, ">", Comparison::Greater),
+    ];
+    let comparison = comparisons
+        .into_iter()
+        .find(|&(token, _, _)| state.lexer.matches(token))
+        .map_or(Comparison::Equal, |(_, repr, comparison)| {
+            spec.text.push_str(repr);
+            comparison
+        });
+    let threshold = argument(state, spec, kind)?;
+    Ok(Range {
+        comparison,
+        threshold,
+    })
+}
+
+/// Parse an argument which must be preceded by a sign, such as `+1` or `-1`.
+fn signed(
+    state: &mut State<impl Rng>,
+    spec: &mut Spec,
+    kind: Argument,
+) -> Result<BigInt, EvalError> {
+    let negative = if state.lexer.matches(Token::Plus) {
+        false
+    } else if state.lexer.matches(Token::Minus) {
+        true
     } else {
-        BigUint::one()
+        return Err(EvalError::UnexpectedToken);
     };
-    Ok(Parse {
-        roll_len,
-        die_size: parse_validated(die_size),
-        drop_least: parse_arg!(drop_least),
-        drop_greatest: parse_arg!(drop_greatest),
-        count_greater: parse_arg!(count_greater),
-        count_lesser: parse_arg!(count_lesser),
+    spec.text.push(if negative { '-' } else { '+' });
+    let magnitude = BigInt::from(argument(state, spec, kind)?);
+    Ok(if negative { -magnitude } else { magnitude })
+}
+
+/// Parse the faces that become other faces, such as `{1: 6, 2: 5}`.
+fn face_map_argument(
+    state: &mut State<impl Rng>,
+    spec: &mut Spec,
+) -> Result<Box<[(BigUint, BigUint)]>, EvalError> {
+    if !state.lexer.matches(Token::OpeningBrace) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    spec.text.push('{');
+    let mut map = Vec::new();
+    loop {
+        let from = argument(state, spec, Argument::Map)?;
+        if !state.lexer.matches(Token::Colon) {
+            return Err(EvalError::UnexpectedToken);
+        }
+        spec.text.push_str(": ");
+        let to = argument(state, spec, Argument::Map)?;
+        map.push((from, to));
+        if !state.lexer.matches(Token::Comma) {
+            break;
+        }
+        spec.text.push_str(", ");
+    }
+    if !state.lexer.matches(Token::ClosingBrace) {
+        return Err(EvalError::UnexpectedToken);
+    }
+    spec.text.push('}');
+    Ok(map.into_boxed_slice())
+}
+
+pub fn natural(value: &Value, kind: Argument) -> Result<BigUint, EvalError> {
+    value.to_natural().map_err(|error| match error {
+        NaturalError::Negative => ArgumentError::Negative(kind).into(),
+        NaturalError::Fractional => ArgumentError::Fractional(kind).into(),
+        NaturalError::Type(error) => error.into(),
     })
 }
 
-fn split_once_number(s: &str) -> (&str, &str) {
-    let mid = s
-        .bytes()
-        .position(|byte| !byte.is_ascii_digit())
-        .unwrap_or(s.len());
-    s.split_at(mid)
+impl From<ParseError> for EvalError {
+    fn from(error: ParseError) -> Self {
+        Self::Roll(super::RollError::Parse(error))
+    }
+}
+
+impl From<ArgumentError> for EvalError {
+    fn from(error: ArgumentError) -> Self {
+        Self::Roll(super::RollError::Argument(error))
+    }
 }
@@ -1,68 +1,268 @@
 mod display;
+mod narrative;
 
-use super::parse::Parse;
+use super::parse::{Modifiers, Parse};
 use num::{BigUint, Zero};
 use rand::Rng;
 use std::fmt;
 
+pub use narrative::{Narrative, NarrativeDie};
+
 #[derive(Debug)]
 pub enum Realize {
     Under(RealizeUnder),
     OverDropped(RealizeOverDropped),
     OverFiltered(RealizeOverFiltered),
+    Cortex(RealizeCortex),
+    Savage(RealizeSavage),
+    Narrative(RealizeNarrative),
+    Step(RealizeStep),
 }
 
 #[derive(Debug)]
 pub struct RealizeUnder {
-    pub least: Option<Box<[BigUint]>>,
-    pub lesser: Option<Box<[BigUint]>>,
-    pub middle: Box<[BigUint]>,
-    pub greater: Option<Box<[BigUint]>>,
-    pub greatest: Option<Box<[BigUint]>>,
+    pub die_size: BigUint,
+    pub least: Option<Box<[Die]>>,
+    pub lesser: Option<Box<[Die]>>,
+    pub middle: Box<[Die]>,
+    pub greater: Option<Box<[Die]>>,
+    pub greatest: Option<Box<[Die]>>,
 }
 
 #[derive(Debug)]
 pub struct RealizeOverDropped {
-    pub least: Option<Box<[BigUint]>>,
-    pub middle: Box<[BigUint]>,
-    pub greatest: Option<Box<[BigUint]>>,
+    pub least: Option<Box<[Die]>>,
+    pub middle: Box<[Die]>,
+    pub greatest: Option<Box<[Die]>>,
 }
 
 #[derive(Debug)]
 pub struct RealizeOverFiltered {
-    pub least: Option<Box<[BigUint]>>,
-    pub lesser: Box<[BigUint]>,
-    pub middle: Box<[BigUint]>,
-    pub greater: Box<[BigUint]>,
-    pub greatest: Option<Box<[BigUint]>>,
+    pub least: Option<Box<[Die]>>,
+    pub lesser: Box<[Die]>,
+    pub middle: Box<[Die]>,
+    pub greater: Box<[Die]>,
+    pub greatest: Option<Box<[Die]>>,
+}
+
+/// Die of a roll with the face it came up on and the face it became after any modifiers.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Die {
+    pub face: BigUint,
+    pub rolled: BigUint,
+}
+
+impl Realize {
+    /// Dice that make up the value of a roll, or those that are counted.
+    pub fn kept(&self) -> &[Die] {
+        match self {
+            Self::Under(RealizeUnder { middle, .. }) => middle,
+            _ => &[],
+        }
+    }
+
+    /// Size and rolled face of the only die kept by a roll, which natural-roll rules apply to.
+    pub fn natural(&self) -> Option<(&BigUint, &BigUint)> {
+        match self {
+            Self::Under(RealizeUnder {
+                die_size,
+                lesser: None,
+                middle,
+                greater: None,
+                ..
+            }) if middle.len() == 1 => Some((die_size, &middle[0].rolled)),
+            _ => None,
+        }
+    }
+}
+
+impl Realize {
+    /// Every die of a plain roll in the order they're displayed, or nothing for any other kind of roll.
+    pub fn dice(&self) -> Vec<&Die> {
+        let groups = match self {
+            Self::Under(RealizeUnder {
+                least,
+                lesser,
+                middle,
+                greater,
+                greatest,
+                ..
+            }) => [
+                least.as_deref(),
+                lesser.as_deref(),
+                Some(middle),
+                greater.as_deref(),
+                greatest.as_deref(),
+            ],
+            Self::OverDropped(RealizeOverDropped {
+                least,
+                middle,
+                greatest,
+            }) => [
+                least.as_deref(),
+                Some(middle),
+                greatest.as_deref(),
+                None,
+                None,
+            ],
+            Self::OverFiltered(RealizeOverFiltered {
+                least,
+                lesser,
+                middle,
+                greater,
+                greatest,
+            }) => [
+                least.as_deref(),
+                Some(lesser),
+                Some(middle),
+                Some(greater),
+                greatest.as_deref(),
+            ],
+            _ => [None; 5],
+        };
+        groups.into_iter().flatten().flatten().collect()
+    }
+}
+
+impl RealizeUnder {
+    /// Every die of the roll whether it's kept, dropped or counted.
+    pub fn faces(&self) -> impl Iterator<Item = &Die> {
+        let Self {
+            least,
+            lesser,
+            middle,
+            greater,
+            greatest,
+            ..
+        } = self;
+
+        [least, lesser, greater, greatest]
+            .into_iter()
+            .flatten()
+            .chain([middle])
+            .flat_map(|dice| dice.iter())
+    }
+}
+
+#[derive(Debug)]
+pub struct RealizeCortex {
+    pub dice: Box<[CortexDie]>,
+}
+
+#[derive(Debug)]
+pub struct CortexDie {
+    pub size: BigUint,
+    pub face: BigUint,
+    pub role: CortexRole,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CortexRole {
+    Total,
+    Effect,
+    Hitch,
+    Unused,
+}
+
+#[derive(Debug)]
+pub struct RealizeSavage {
+    pub trait_die: Ace,
+    pub wild_die: Ace,
+}
+
+impl RealizeSavage {
+    /// Whether the wild die is taken in place of the trait die.
+    pub fn wild_is_kept(&self) -> bool {
+        self.wild_die.total() > self.trait_die.total()
+    }
+}
+
+#[derive(Debug)]
+pub struct RealizeNarrative {
+    pub dice: Box<[NarrativeDie]>,
+}
+
+impl RealizeNarrative {
+    pub fn symbols(&self) -> crate::value::Symbols {
+        narrative::symbols(&self.dice)
+    }
+}
+
+#[derive(Debug)]
+pub struct RealizeStep {
+    pub dice: Box<[Ace]>,
+}
+
+/// Die which is rolled again and added to whenever it rolls its maximum.
+#[derive(Debug)]
+pub struct Ace {
+    pub size: BigUint,
+    pub faces: Box<[BigUint]>,
+}
+
+impl Ace {
+    pub fn total(&self) -> BigUint {
+        self.faces.iter().sum()
+    }
 }
 
 #[derive(Debug)]
 pub enum RealizeError {
     DieSizeIsZero,
+    AceDieSizeIsOne,
     RollLenExceedsMaximum,
+    UniqueRollLenExceedsDieSize,
 }
 
 impl fmt::Display for RealizeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::DieSizeIsZero => write!(f, "die size is zero"),
+            Self::AceDieSizeIsOne => write!(f, "acing die size is one"),
             Self::RollLenExceedsMaximum => write!(f, "roll length exceeds maximum"),
+            Self::UniqueRollLenExceedsDieSize => {
+                write!(f, "more unique dice are rolled than the die has faces")
+            }
+        }
+    }
+}
+
+impl RealizeError {
+    pub fn class(&self) -> crate::ErrorClass {
+        match self {
+            Self::DieSizeIsZero | Self::AceDieSizeIsOne | Self::UniqueRollLenExceedsDieSize => {
+                crate::ErrorClass::Evaluation
+            }
+            Self::RollLenExceedsMaximum => crate::ErrorClass::Limit,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DieSizeIsZero => "die_size_is_zero",
+            Self::AceDieSizeIsOne => "ace_die_size_is_one",
+            Self::RollLenExceedsMaximum => "roll_len_exceeds_maximum",
+            Self::UniqueRollLenExceedsDieSize => "unique_roll_len_exceeds_die_size",
         }
     }
 }
 
-pub fn main(parse: Parse, rng: &mut impl Rng) -> Result<Realize, RealizeError> {
+pub fn main(parse: &Parse, rng: &mut impl Rng) -> Result<Realize, RealizeError> {
+    let roll = sample(parse, rng)?;
+    partition(parse, roll)
+}
+
+/// Roll the dice of a roll and apply its modifiers to them.
+fn sample(parse: &Parse, rng: &mut impl Rng) -> Result<Vec<Die>, RealizeError> {
     use num::One as _;
     use rand::distributions::Distribution as _;
 
     let Parse {
         roll_len,
         die_size,
-        drop_least,
-        drop_greatest,
-        count_greater,
-        count_lesser,
+        modifiers,
+        unique,
+        ..
     } = parse;
     if die_size.is_zero() {
         return Err(RealizeError::DieSizeIsZero);
@@ -70,27 +270,59 @@ pub fn main(parse: Parse, rng: &mut impl Rng) -> Result<Realize, RealizeError> {
     let roll_len = usize::try_from(roll_len)
         .ok()
         .ok_or(RealizeError::RollLenExceedsMaximum)?;
+    let faces = if *unique {
+        if BigUint::from(roll_len) > *die_size {
+            return Err(RealizeError::UniqueRollLenExceedsDieSize);
+        }
+        sample_unique(roll_len, die_size, rng)
+    } else {
+        rand::distributions::Uniform::new_inclusive(BigUint::one(), die_size)
+            .sample_iter(&mut *rng)
+            .take(roll_len)
+            .collect()
+    };
+    Ok(faces
+        .into_iter()
+        .map(|rolled| Die {
+            face: modify(&rolled, modifiers),
+            rolled,
+        })
+        .collect())
+}
+
+/// Sort dice that have already been rolled into those that a roll keeps, drops and counts.
+///
+/// Dice stay in the order they're given in within each group, so that dice given in the order they were displayed keep their numbers.
+pub fn partition(parse: &Parse, roll: Vec<Die>) -> Result<Realize, RealizeError> {
+    let Parse {
+        die_size,
+        drop_least,
+        drop_greatest,
+        count_greater,
+        count_lesser,
+        ..
+    } = parse;
+    let roll_len = roll.len();
     // since `roll_len` can only be at most `usize::MAX`, `drop_least` and `drop_greatest` need to only be at most `usize::MAX` too
-    let mut drop_least = drop_least.map(saturating_cast);
-    let mut drop_greatest = drop_greatest.map(saturating_cast);
-    let mut roll = rand::distributions::Uniform::new_inclusive(BigUint::one(), die_size)
-        .sample_iter(&mut *rng)
-        .take(roll_len)
-        .collect::<Vec<_>>();
-    roll.sort();
-    let over_dropped = drop_least.unwrap_or(0) + drop_greatest.unwrap_or(0) >= roll.len();
+    let mut drop_least = drop_least.clone().map(saturating_cast);
+    let mut drop_greatest = drop_greatest.clone().map(saturating_cast);
+    // sort positions rather than the dice themselves so that each group can be put back in order
+    let mut sorted = (0..roll_len).collect::<Vec<_>>();
+    sorted.sort_by(|&a, &b| roll[a].cmp(&roll[b]));
+    let over_dropped = drop_least.unwrap_or(0) + drop_greatest.unwrap_or(0) >= roll_len;
     if over_dropped {
         let drop_least_inverted = drop_least.map(|amount| roll_len.saturating_sub(amount));
         let drop_greatest_inverted = drop_greatest.map(|amount| roll_len.saturating_sub(amount));
         drop_least = drop_greatest_inverted;
         drop_greatest = drop_least_inverted;
     }
-    let greatest = drop_greatest.map(|amount| finalize(take_greatest(&mut roll, amount), rng));
-    let least = drop_least.map(|amount| finalize(take_least(&mut roll, amount), rng));
+    let finalize = |group| finalize(group, &roll);
+    let greatest = drop_greatest.map(|amount| finalize(take_greatest(&mut sorted, amount)));
+    let least = drop_least.map(|amount| finalize(take_least(&mut sorted, amount)));
     if over_dropped {
         return Ok(Realize::OverDropped(RealizeOverDropped {
             least,
-            middle: finalize(roll, rng),
+            middle: finalize(sorted),
             greatest,
         }));
     }
@@ -100,32 +332,238 @@ pub fn main(parse: Parse, rng: &mut impl Rng) -> Result<Realize, RealizeError> {
         .filter(|(count_greater, count_lesser)| count_greater > count_lesser);
     if let Some((count_greater, count_lesser)) = overlapping_bounds {
         // swap thresholds by swapping where they're used
-        let greater = finalize(take_greater(&mut roll, count_greater), rng);
-        let lesser = finalize(take_lesser(&mut roll, count_lesser), rng);
+        let greater = finalize(take_greater(&mut sorted, &roll, count_greater));
+        let lesser = finalize(take_lesser(&mut sorted, &roll, count_lesser));
         return Ok(Realize::OverFiltered(RealizeOverFiltered {
             least,
             lesser,
-            middle: finalize(roll, rng),
+            middle: finalize(sorted),
             greater,
             greatest,
         }));
     }
-    let greater = count_lesser.map(|threshold| finalize(take_greater(&mut roll, &threshold), rng));
-    let lesser = count_greater.map(|threshold| finalize(take_lesser(&mut roll, &threshold), rng));
+    let greater = count_lesser
+        .as_ref()
+        .map(|threshold| finalize(take_greater(&mut sorted, &roll, threshold)));
+    let lesser = count_greater
+        .as_ref()
+        .map(|threshold| finalize(take_lesser(&mut sorted, &roll, threshold)));
     Ok(Realize::Under(RealizeUnder {
+        die_size: die_size.clone(),
         least,
         lesser,
-        middle: finalize(roll, rng),
+        middle: finalize(sorted),
         greater,
         greatest,
     }))
 }
 
-fn finalize(mut roll: Vec<BigUint>, rng: &mut impl Rng) -> Box<[BigUint]> {
-    use rand::seq::SliceRandom;
+/// Roll a single die again as part of a roll, applying its modifiers.
+///
+/// A die of a roll without replacement can't come up on a face that's `taken` by the roll's other dice.
+pub fn reroll(parse: &Parse, taken: &[&BigUint], rng: &mut impl Rng) -> Die {
+    use num::One as _;
+    use rand::distributions::Distribution as _;
+
+    if !parse.unique {
+        let rolled = rand::distributions::Uniform::new_inclusive(BigUint::one(), &parse.die_size)
+            .sample(rng);
+        return replace(parse, rolled);
+    }
+    let mut taken = taken.to_vec();
+    taken.sort();
+    taken.dedup();
+    let free = &parse.die_size - BigUint::from(taken.len());
+    // pick among the free faces and then skip over the taken ones
+    let mut rolled = rand::distributions::Uniform::new_inclusive(BigUint::one(), free).sample(rng);
+    for face in taken {
+        if *face <= rolled {
+            rolled += 1u32;
+        }
+    }
+    replace(parse, rolled)
+}
+
+/// Die of a roll that came up on the given face, applying its modifiers.
+pub fn replace(parse: &Parse, rolled: BigUint) -> Die {
+    Die {
+        face: modify(&rolled, &parse.modifiers),
+        rolled,
+    }
+}
+
+/// Roll a Cortex Prime pool of dice with the given sizes.
+///
+/// The two greatest faces that aren't ones (hitches) are added together for the total and the greatest remaining die is the effect die.
+/// Ties are broken by using lesser dice for the total so that greater dice are left for the effect die.
+pub fn cortex(sizes: Vec<BigUint>, rng: &mut impl Rng) -> Result<RealizeCortex, RealizeError> {
+    use num::One as _;
+    use rand::distributions::Distribution as _;
+
+    if sizes.iter().any(Zero::is_zero) {
+        return Err(RealizeError::DieSizeIsZero);
+    }
+    let rolls = sizes
+        .into_iter()
+        .map(|size| {
+            let face = rand::distributions::Uniform::new_inclusive(BigUint::one(), &size)
+                .sample(&mut *rng);
+            (size, face)
+        })
+        .collect();
+    Ok(cortex_roles(rolls))
+}
+
+/// Give each die of a Cortex Prime pool, rolled as its size and face, its role in the result.
+fn cortex_roles(rolls: Vec<(BigUint, BigUint)>) -> RealizeCortex {
+    use num::One as _;
+
+    let mut dice = rolls
+        .into_iter()
+        .map(|(size, face)| {
+            let role = if face.is_one() {
+                CortexRole::Hitch
+            } else {
+                CortexRole::Unused
+            };
+            CortexDie { size, face, role }
+        })
+        .collect::<Box<[_]>>();
+    let mut order = (0..dice.len())
+        .filter(|&index| dice[index].role != CortexRole::Hitch)
+        .collect::<Vec<_>>();
+    order.sort_by(|&a, &b| {
+        let (a, b) = (&dice[a], &dice[b]);
+        b.face.cmp(&a.face).then(a.size.cmp(&b.size))
+    });
+    let (total, rest) = order.split_at(order.len().min(2));
+    for &index in total {
+        dice[index].role = CortexRole::Total;
+    }
+    if let Some(&effect) = rest
+        .iter()
+        .max_by(|&&a, &&b| dice[a].size.cmp(&dice[b].size))
+    {
+        dice[effect].role = CortexRole::Effect;
+    }
+    RealizeCortex { dice }
+}
+
+/// Roll a Savage Worlds trait die of the given size alongside a d6 wild die, both of which ace.
+pub fn savage(trait_size: BigUint, rng: &mut impl Rng) -> Result<RealizeSavage, RealizeError> {
+    Ok(RealizeSavage {
+        trait_die: ace(trait_size, rng)?,
+        wild_die: ace(BigUint::from(6u32), rng)?,
+    })
+}
+
+/// Roll narrative dice of the given kinds.
+pub fn narrative(kinds: Vec<Narrative>, rng: &mut impl Rng) -> RealizeNarrative {
+    use num::One as _;
+    use rand::distributions::Distribution as _;
+
+    let dice = kinds
+        .into_iter()
+        .map(|kind| NarrativeDie {
+            kind,
+            face: rand::distributions::Uniform::new_inclusive(BigUint::one(), kind.size())
+                .sample(&mut *rng),
+        })
+        .collect();
+    RealizeNarrative { dice }
+}
+
+/// Roll exploding dice of the given sizes.
+pub fn step(sizes: &[BigUint], rng: &mut impl Rng) -> Result<RealizeStep, RealizeError> {
+    let dice = sizes
+        .iter()
+        .map(|size| ace(size.clone(), rng))
+        .collect::<Result<_, _>>()?;
+    Ok(RealizeStep { dice })
+}
+
+fn ace(size: BigUint, rng: &mut impl Rng) -> Result<Ace, RealizeError> {
+    use num::One as _;
+    use rand::distributions::Distribution as _;
 
-    roll.shuffle(rng);
-    roll.into_boxed_slice()
+    if size.is_zero() {
+        return Err(RealizeError::DieSizeIsZero);
+    }
+    // a d1 would ace forever
+    if size.is_one() {
+        return Err(RealizeError::AceDieSizeIsOne);
+    }
+    let distribution = rand::distributions::Uniform::new_inclusive(BigUint::one(), &size);
+    let mut faces = vec![distribution.sample(&mut *rng)];
+    while faces.last() == Some(&size) {
+        faces.push(distribution.sample(&mut *rng));
+    }
+    Ok(Ace {
+        size,
+        faces: faces.into_boxed_slice(),
+    })
+}
+
+/// Sample `amount` distinct faces of a die using Floyd's algorithm, which needs exactly one sample per face.
+fn sample_unique(amount: usize, die_size: &BigUint, rng: &mut impl Rng) -> Vec<BigUint> {
+    use num::One as _;
+    use rand::{distributions::Distribution as _, seq::SliceRandom as _};
+    use std::collections::BTreeSet;
+
+    let mut faces = BTreeSet::new();
+    let mut bound = die_size - BigUint::from(amount);
+    while faces.len() < amount {
+        bound += 1u32;
+        let face =
+            rand::distributions::Uniform::new_inclusive(BigUint::one(), &bound).sample(&mut *rng);
+        if faces.contains(&face) {
+            faces.insert(bound.clone());
+        } else {
+            faces.insert(face);
+        }
+    }
+    // the set is in order of face, which would give away which dice are greater
+    let mut faces = faces.into_iter().collect::<Vec<_>>();
+    faces.shuffle(rng);
+    faces
+}
+
+/// Apply the modifiers of a roll to a face in turn: mapping it, adding to it and then clamping it.
+///
+/// Faces can't go below zero.
+fn modify(rolled: &BigUint, modifiers: &Modifiers) -> BigUint {
+    use num::BigInt;
+
+    let Modifiers {
+        map,
+        each,
+        minimum,
+        maximum,
+    } = modifiers;
+
+    let mut face = map
+        .iter()
+        .find(|(from, _)| from == rolled)
+        .map_or(rolled, |(_, to)| to)
+        .clone();
+    if let Some(each) = each {
+        face = (BigInt::from(face) + each)
+            .to_biguint()
+            .unwrap_or_else(BigUint::zero);
+    }
+    if let Some(minimum) = minimum {
+        face = face.max(minimum.clone());
+    }
+    if let Some(maximum) = maximum {
+        face = face.min(maximum.clone());
+    }
+    face
+}
+
+/// Dice of a roll at the given positions, in the order they were rolled.
+fn finalize(mut positions: Vec<usize>, roll: &[Die]) -> Box<[Die]> {
+    positions.sort_unstable();
+    positions.into_iter().map(|at| roll[at].clone()).collect()
 }
 
 // todo this should really be part of the `num` crate
@@ -141,13 +579,13 @@ fn take_least<T>(sorted: &mut Vec<T>, amount: usize) -> Vec<T> {
     split_off_front(sorted, amount)
 }
 
-fn take_greater<T: Ord>(sorted: &mut Vec<T>, threshold: &T) -> Vec<T> {
-    let at = sorted.partition_point(|value| value < threshold);
+fn take_greater(sorted: &mut Vec<usize>, roll: &[Die], threshold: &BigUint) -> Vec<usize> {
+    let at = sorted.partition_point(|&die| roll[die].face < *threshold);
     split_off_back(sorted, at)
 }
 
-fn take_lesser<T: Ord>(sorted: &mut Vec<T>, threshold: &T) -> Vec<T> {
-    let at = sorted.partition_point(|value| value <= threshold);
+fn take_lesser(sorted: &mut Vec<usize>, roll: &[Die], threshold: &BigUint) -> Vec<usize> {
+    let at = sorted.partition_point(|&die| roll[die].face <= *threshold);
     split_off_front(sorted, at)
 }
 
@@ -160,3 +598,111 @@ fn split_off_front<T>(vec: &mut Vec<T>, at: usize) -> Vec<T> {
 fn split_off_back<T>(vec: &mut Vec<T>, at: usize) -> Vec<T> {
     vec.split_off(at)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng as _};
+
+    fn unique(roll_len: u32, die_size: u32) -> Parse {
+        Parse {
+            roll_len: BigUint::from(roll_len),
+            die_size: BigUint::from(die_size),
+            drop_least: None,
+            drop_greatest: None,
+            count_greater: None,
+            count_lesser: None,
+            critical: None,
+            fumble: None,
+            modifiers: Modifiers::default(),
+            unique: true,
+        }
+    }
+
+    fn roles(rolls: &[(u32, u32)]) -> Vec<CortexRole> {
+        let rolls = rolls
+            .iter()
+            .map(|&(size, face)| (BigUint::from(size), BigUint::from(face)))
+            .collect();
+        cortex_roles(rolls)
+            .dice
+            .iter()
+            .map(|die| die.role)
+            .collect()
+    }
+
+    #[test]
+    fn cortex_total_and_effect() {
+        use CortexRole::*;
+
+        // the greatest faces make the total and the greatest remaining die is the effect
+        assert_eq!(
+            roles(&[(6, 5), (8, 7), (10, 2), (4, 3)]),
+            [Total, Total, Effect, Unused]
+        );
+        // an effect die is chosen by its size rather than its face
+        assert_eq!(
+            roles(&[(12, 2), (6, 6), (8, 5), (4, 4)]),
+            [Effect, Total, Total, Unused]
+        );
+    }
+
+    #[test]
+    fn cortex_ties() {
+        use CortexRole::*;
+
+        // tied faces are totalled with lesser dice so that greater dice are left for the effect
+        assert_eq!(
+            roles(&[(12, 4), (6, 4), (8, 4), (10, 4)]),
+            [Effect, Total, Total, Unused]
+        );
+    }
+
+    #[test]
+    fn cortex_hitches() {
+        use CortexRole::*;
+
+        // hitches are neither totalled nor the effect, even when nothing else is left
+        assert_eq!(roles(&[(12, 1), (6, 3), (4, 2)]), [Hitch, Total, Total]);
+        assert_eq!(roles(&[(8, 1), (6, 1)]), [Hitch, Hitch]);
+    }
+
+    #[test]
+    fn unique_never_repeats() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for roll_len in 0..=6 {
+            for _ in 0..100 {
+                let mut faces = sample(&unique(roll_len, 6), &mut rng)
+                    .unwrap()
+                    .into_iter()
+                    .map(|die| u32::try_from(die.rolled).unwrap())
+                    .collect::<Vec<_>>();
+                assert_eq!(faces.len(), roll_len as usize);
+                faces.sort();
+                faces.dedup();
+                assert_eq!(faces.len(), roll_len as usize, "{faces:?}");
+                assert!(faces.iter().all(|face| (1..=6).contains(face)));
+            }
+        }
+    }
+
+    #[test]
+    fn unique_exceeds_die_size() {
+        let mut rng = StdRng::seed_from_u64(0);
+        assert!(matches!(
+            sample(&unique(7, 6), &mut rng),
+            Err(RealizeError::UniqueRollLenExceedsDieSize)
+        ));
+    }
+
+    #[test]
+    fn unique_reroll_avoids_taken() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let taken = [1u32, 3, 4, 6].map(BigUint::from);
+        let taken = taken.iter().collect::<Vec<_>>();
+        for _ in 0..100 {
+            let rolled = reroll(&unique(5, 6), &taken, &mut rng).rolled;
+            assert!(rolled == BigUint::from(2u32) || rolled == BigUint::from(5u32));
+        }
+    }
+}
@@ -8,6 +8,10 @@ impl fmt::Display for super::Realize {
             Self::Under(inner) => write!(f, "{inner}"),
             Self::OverDropped(inner) => write!(f, "{inner}"),
             Self::OverFiltered(inner) => write!(f, "{inner}"),
+            Self::Cortex(inner) => write!(f, "{inner}"),
+            Self::Savage(inner) => write!(f, "{inner}"),
+            Self::Narrative(inner) => write!(f, "{inner}"),
+            Self::Step(inner) => write!(f, "{inner}"),
         }
     }
 }
@@ -20,6 +24,7 @@ impl fmt::Display for super::RealizeUnder {
             middle,
             greater,
             greatest,
+            ..
         } = self;
 
         if let Some(least) = least {
@@ -97,6 +102,93 @@ impl fmt::Display for RealizeOverFiltered {
     }
 }
 
+impl fmt::Display for super::Die {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { face, rolled } = self;
+
+        if face == rolled {
+            write!(f, "{face}")
+        } else {
+            write!(f, "{rolled}->{face}")
+        }
+    }
+}
+
+impl fmt::Display for super::RealizeCortex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { dice } = self;
+
+        write!(f, "[")?;
+        display_spaced(dice.iter(), f)?;
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for super::CortexDie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use super::CortexRole::*;
+
+        let Self { size, face, role } = self;
+        match role {
+            Total => write!(f, "d{size}:{face}"),
+            Effect => write!(f, "d{size}:{face}*"),
+            Hitch => write!(f, "d{size}:{face}!"),
+            Unused => write!(f, "~d{size}:{face}~"),
+        }
+    }
+}
+
+impl fmt::Display for super::RealizeSavage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            trait_die,
+            wild_die,
+        } = self;
+
+        if self.wild_is_kept() {
+            write!(f, "[~{trait_die}~ {wild_die}]")
+        } else {
+            write!(f, "[{trait_die} ~{wild_die}~]")
+        }
+    }
+}
+
+impl fmt::Display for super::RealizeNarrative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { dice } = self;
+
+        write!(f, "[")?;
+        display_spaced(dice.iter(), f)?;
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for super::RealizeStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { dice } = self;
+
+        write!(f, "[")?;
+        display_spaced(dice.iter(), f)?;
+        write!(f, "]")
+    }
+}
+
+impl fmt::Display for super::Ace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { size, faces } = self;
+
+        write!(f, "d{size}:")?;
+        let mut faces = faces.iter();
+        if let Some(first) = faces.next() {
+            write!(f, "{first}")?;
+        }
+        for next in faces {
+            write!(f, "+{next}")?;
+        }
+        Ok(())
+    }
+}
+
 fn display_spaced<I>(sequence: I, f: &mut fmt::Formatter) -> fmt::Result
 where
     I: IntoIterator,
@@ -0,0 +1,198 @@
+use crate::value::Symbols;
+use num::BigUint;
+use std::fmt;
+
+/// Narrative die of the Genesys system, whose faces are sets of symbols.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Narrative {
+    Boost,
+    Setback,
+    Ability,
+    Difficulty,
+    Proficiency,
+    Challenge,
+}
+
+/// Symbol printed on a face of a narrative die.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Symbol {
+    Success,
+    Failure,
+    Advantage,
+    Threat,
+    Triumph,
+    Despair,
+}
+
+use Symbol::*;
+
+const BOOST: &[&[Symbol]] = &[
+    &[],
+    &[],
+    &[Success],
+    &[Success, Advantage],
+    &[Advantage, Advantage],
+    &[Advantage],
+];
+const SETBACK: &[&[Symbol]] = &[&[], &[], &[Failure], &[Failure], &[Threat], &[Threat]];
+const ABILITY: &[&[Symbol]] = &[
+    &[],
+    &[Success],
+    &[Success],
+    &[Success, Success],
+    &[Advantage],
+    &[Advantage],
+    &[Success, Advantage],
+    &[Advantage, Advantage],
+];
+const DIFFICULTY: &[&[Symbol]] = &[
+    &[],
+    &[Failure],
+    &[Failure, Failure],
+    &[Threat],
+    &[Threat],
+    &[Threat],
+    &[Threat, Threat],
+    &[Failure, Threat],
+];
+const PROFICIENCY: &[&[Symbol]] = &[
+    &[],
+    &[Success],
+    &[Success],
+    &[Success, Success],
+    &[Success, Success],
+    &[Advantage],
+    &[Success, Advantage],
+    &[Success, Advantage],
+    &[Success, Advantage],
+    &[Advantage, Advantage],
+    &[Advantage, Advantage],
+    &[Triumph],
+];
+const CHALLENGE: &[&[Symbol]] = &[
+    &[],
+    &[Failure],
+    &[Failure],
+    &[Failure, Failure],
+    &[Failure, Failure],
+    &[Threat],
+    &[Threat],
+    &[Failure, Threat],
+    &[Failure, Threat],
+    &[Threat, Threat],
+    &[Threat, Threat],
+    &[Despair],
+];
+
+impl Narrative {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "boost" => Self::Boost,
+            "setback" => Self::Setback,
+            "ability" => Self::Ability,
+            "difficulty" => Self::Difficulty,
+            "proficiency" => Self::Proficiency,
+            "challenge" => Self::Challenge,
+            _ => return None,
+        })
+    }
+
+    fn faces(self) -> &'static [&'static [Symbol]] {
+        match self {
+            Self::Boost => BOOST,
+            Self::Setback => SETBACK,
+            Self::Ability => ABILITY,
+            Self::Difficulty => DIFFICULTY,
+            Self::Proficiency => PROFICIENCY,
+            Self::Challenge => CHALLENGE,
+        }
+    }
+
+    pub fn size(self) -> BigUint {
+        BigUint::from(self.faces().len())
+    }
+}
+
+impl fmt::Display for Narrative {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Boost => write!(f, "boost"),
+            Self::Setback => write!(f, "setback"),
+            Self::Ability => write!(f, "ability"),
+            Self::Difficulty => write!(f, "difficulty"),
+            Self::Proficiency => write!(f, "proficiency"),
+            Self::Challenge => write!(f, "challenge"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NarrativeDie {
+    pub kind: Narrative,
+    pub face: BigUint,
+}
+
+impl NarrativeDie {
+    fn symbols(&self) -> &'static [Symbol] {
+        let index = usize::try_from(&self.face).expect("face should be within the die") - 1;
+        self.kind.faces()[index]
+    }
+
+    /// Names of the symbols on the face the die came up on, such as `success`.
+    pub fn symbol_names(&self) -> impl Iterator<Item = &'static str> {
+        self.symbols().iter().map(|symbol| match symbol {
+            Success => "success",
+            Failure => "failure",
+            Advantage => "advantage",
+            Threat => "threat",
+            Triumph => "triumph",
+            Despair => "despair",
+        })
+    }
+}
+
+impl fmt::Display for NarrativeDie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbols = self.symbols();
+        write!(f, "{}:", self.kind)?;
+        if symbols.is_empty() {
+            return write!(f, "-");
+        }
+        symbols.iter().try_for_each(|symbol| {
+            write!(
+                f,
+                "{}",
+                match symbol {
+                    Success => 'S',
+                    Failure => 'F',
+                    Advantage => 'A',
+                    Threat => 'T',
+                    Triumph => 'R',
+                    Despair => 'D',
+                }
+            )
+        })
+    }
+}
+
+/// Net the symbols of every die, where a triumph is also a success and a despair is also a failure.
+pub fn symbols(dice: &[NarrativeDie]) -> Symbols {
+    let mut symbols = Symbols::default();
+    for symbol in dice.iter().flat_map(NarrativeDie::symbols) {
+        match symbol {
+            Success => symbols.success += 1,
+            Failure => symbols.success -= 1,
+            Advantage => symbols.advantage += 1,
+            Threat => symbols.advantage -= 1,
+            Triumph => {
+                symbols.success += 1;
+                symbols.triumph += 1u32;
+            }
+            Despair => {
+                symbols.success -= 1;
+                symbols.despair += 1u32;
+            }
+        }
+    }
+    symbols
+}
@@ -1,26 +1,32 @@
-use super::realize::{self, Realize};
-use num::{BigUint, Zero as _};
+use super::realize::{self, CortexRole, Realize};
+use num::BigUint;
 
-pub fn main(realize: Realize) -> crate::Value {
-    crate::Value::from(realize.value())
+pub fn main(realize: &Realize) -> crate::Value {
+    realize.value()
 }
 
 trait Value {
-    fn value(self) -> BigUint;
+    fn value(&self) -> crate::Value;
 }
 
 impl Value for Realize {
-    fn value(self) -> BigUint {
+    fn value(&self) -> crate::Value {
         match self {
             Self::Under(inner) => inner.value(),
             Self::OverDropped(inner) => inner.value(),
             Self::OverFiltered(inner) => inner.value(),
+            Self::Cortex(inner) => inner.value(),
+            Self::Savage(inner) => inner.value(),
+            Self::Narrative(inner) => crate::Value::Symbols(inner.symbols()),
+            Self::Step(inner) => {
+                crate::Value::from(inner.dice.iter().map(realize::Ace::total).sum::<BigUint>())
+            }
         }
     }
 }
 
 impl Value for realize::RealizeUnder {
-    fn value(self) -> BigUint {
+    fn value(&self) -> crate::Value {
         let Self {
             lesser,
             middle,
@@ -29,21 +35,54 @@ impl Value for realize::RealizeUnder {
         } = self;
 
         if lesser.is_some() || greater.is_some() {
-            BigUint::from(middle.len())
+            crate::Value::from(middle.len())
         } else {
-            middle.iter().sum()
+            crate::Value::from(middle.iter().map(|die| &die.face).sum::<BigUint>())
         }
     }
 }
 
 impl Value for realize::RealizeOverDropped {
-    fn value(self) -> BigUint {
-        BigUint::zero()
+    fn value(&self) -> crate::Value {
+        crate::Value::zero()
     }
 }
 
 impl Value for realize::RealizeOverFiltered {
-    fn value(self) -> BigUint {
-        BigUint::zero()
+    fn value(&self) -> crate::Value {
+        crate::Value::zero()
+    }
+}
+
+impl Value for realize::RealizeCortex {
+    fn value(&self) -> crate::Value {
+        let Self { dice } = self;
+
+        let with_role = |role| dice.iter().filter(move |die| die.role == role);
+        let total = with_role(CortexRole::Total)
+            .map(|die| &die.face)
+            .sum::<BigUint>();
+        // the effect die defaults to a d4 when there are no dice left over
+        let effect = with_role(CortexRole::Effect)
+            .map(|die| die.size.clone())
+            .next()
+            .unwrap_or_else(|| BigUint::from(4u32));
+        let hitches = with_role(CortexRole::Hitch).count();
+        crate::Value::record([
+            ("total", crate::Value::from(total)),
+            ("effect", crate::Value::from(effect)),
+            ("hitches", crate::Value::from(hitches)),
+        ])
+    }
+}
+
+impl Value for realize::RealizeSavage {
+    fn value(&self) -> crate::Value {
+        let kept = if self.wild_is_kept() {
+            &self.wild_die
+        } else {
+            &self.trait_die
+        };
+        crate::Value::from(kept.total())
     }
 }
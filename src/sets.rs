@@ -0,0 +1,177 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use num::BigUint;
+use rand::Rng;
+use std::fmt::{self, Write as _};
+
+/// Parse `sets({expression})` and find the matching sets among the dice kept by its rolls.
+pub fn main(state: &mut State<impl Rng>) -> PartialResult {
+    let start = state.pools.len();
+    super::expression(state)?;
+    if state.skipping {
+        return Ok(pattern(Vec::new()));
+    }
+    let faces = state.pools[start..]
+        .iter()
+        .flat_map(super::roll::Pool::kept)
+        .map(|die| die.face.clone())
+        .collect();
+    Ok(pattern(faces))
+}
+
+/// Parse `ore({n}, expert {face}, wiggle)` and roll a One Roll Engine pool of `n` d10 along with any expert and wiggle dice.
+///
+/// Expert dice are set to their face before rolling and wiggle dice are set after rolling to widen the widest set.
+pub fn ore(state: &mut State<impl Rng>) -> PartialResult {
+    let roll_len = super::expression(state)?;
+    let mut experts = Vec::new();
+    let mut wiggles = 0usize;
+    while state.lexer.matches(Token::Comma) {
+        state.output.push_str(", ");
+        if state.lexer.matches_word("expert") {
+            state.output.push_str("expert ");
+            let face = super::expression(state)?;
+            if state.skipping {
+                face.as_number()?;
+            } else {
+                experts.push(face.to_natural().map_err(|_| SetsError::Expert(face))?);
+            }
+        } else if state.lexer.matches_word("wiggle") {
+            state.output.push_str("wiggle");
+            wiggles += 1;
+        } else {
+            return Err(EvalError::UnexpectedToken);
+        }
+    }
+    if state.skipping {
+        roll_len.as_number()?;
+        return Ok(pattern(Vec::new()));
+    }
+    let ten = BigUint::from(10u32);
+    if let Some(face) = experts
+        .iter()
+        .find(|&face| *face < BigUint::from(1u32) || *face > ten)
+    {
+        return Err(SetsError::Expert(Value::from(face.clone())).into());
+    }
+    state.output.push_str(": ");
+    let mut faces = super::roll::plain(state, roll_len, ten.clone())?;
+    for face in &experts {
+        write!(state.output, " expert:{face}")?;
+    }
+    faces.extend(experts);
+    for _ in 0..wiggles {
+        let face = wiggle(&faces).unwrap_or_else(|| ten.clone());
+        write!(state.output, " wiggle:{face}")?;
+        faces.push(face);
+    }
+    Ok(pattern(faces))
+}
+
+/// Face that a wiggle die should take, joining the widest and then tallest set, or the tallest die when there are no sets.
+fn wiggle(faces: &[BigUint]) -> Option<BigUint> {
+    runs(faces)
+        .into_iter()
+        .max()
+        .map(|(_, height)| height.clone())
+}
+
+/// Record of the matching faces among dice, labeled with the sets as width by height, such as `3x7, 2x2`.
+///
+/// The record holds every `sets` from widest to narrowest, the `width` and `height` of the widest, how many faces make `pairs` and `triples`, and the length of the longest `straight`.
+fn pattern(faces: Vec<BigUint>) -> Value {
+    let runs = runs(&faces);
+    let mut sets = runs
+        .iter()
+        .filter(|&&(width, _)| width >= 2)
+        .collect::<Vec<_>>();
+    sets.sort_by(|a, b| b.cmp(a));
+    let label = match sets.is_empty() {
+        true => "no sets".into(),
+        false => sets
+            .iter()
+            .map(|(width, height)| format!("{width}x{height}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    let (width, height) = sets
+        .first()
+        .map_or((Value::zero(), Value::zero()), |&(width, height)| {
+            (Value::from(*width), Value::from((*height).clone()))
+        });
+    let pairs = sets.len();
+    let triples = runs.iter().filter(|&&(width, _)| width >= 3).count();
+    Value::Labeled(
+        Box::new(Value::record([
+            (
+                "sets",
+                sets.iter()
+                    .map(|(width, height)| {
+                        Value::record([
+                            ("width", Value::from(*width)),
+                            ("height", Value::from((*height).clone())),
+                        ])
+                    })
+                    .collect(),
+            ),
+            ("width", width),
+            ("height", height),
+            ("pairs", Value::from(pairs)),
+            ("triples", Value::from(triples)),
+            ("straight", Value::from(straight(&runs))),
+        ])),
+        label.into(),
+    )
+}
+
+/// Number of dice showing each face, from the least face to the greatest.
+fn runs(faces: &[BigUint]) -> Vec<(usize, &BigUint)> {
+    let mut sorted = faces.iter().collect::<Vec<_>>();
+    sorted.sort();
+    sorted
+        .chunk_by(|a, b| a == b)
+        .map(|run| (run.len(), run[0]))
+        .collect()
+}
+
+/// Length of the longest run of consecutive faces.
+fn straight(runs: &[(usize, &BigUint)]) -> usize {
+    let mut longest = 0;
+    let mut length = 0;
+    let mut previous = None::<&BigUint>;
+    for &(_, face) in runs {
+        length = match previous {
+            Some(previous) if *face == previous + 1u32 => length + 1,
+            _ => 1,
+        };
+        longest = longest.max(length);
+        previous = Some(face);
+    }
+    longest
+}
+
+#[derive(Debug)]
+pub enum SetsError {
+    Expert(Value),
+}
+
+impl SetsError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Expert(_) => "invalid_expert_die",
+        }
+    }
+}
+
+impl From<SetsError> for EvalError {
+    fn from(error: SetsError) -> Self {
+        Self::Sets(error)
+    }
+}
+
+impl fmt::Display for SetsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expert(face) => write!(f, "expert die cannot show {face}"),
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use super::{EvalError, PartialResult, State, Value};
+use num::{BigInt, BigUint, Integer as _, ToPrimitive as _, Zero as _};
+use rand::Rng;
+use std::fmt::{self, Write as _};
+
+/// Step table of the first and second editions of Earthdawn.
+const EARTHDAWN: &str = "
+1: d4-2
+2: d4-1
+3: d4
+4: d6
+5: d8
+6: d10
+7: d12
+8: 2d6
+9: d8+d6
+10: d10+d6
+11: d10+d8
+12: 2d10
+13: d12+d10
+14: d20+d4
+15: d20+d6
+16: d20+d8
+17: d20+d10
+18: d20+d12
+19: d20+2d6
+20: d20+d8+d6
+21: d20+d10+d6
+22: d20+d10+d8
+23: d20+2d10
+24: d20+d12+d10
+25: d20+d10+d8+d4
+26: d20+d10+d8+d6
+27: d20+d10+2d8
+28: d20+2d10+d8
+29: d20+d12+d10+d8
+30: d20+d10+d8+2d6
+31: d20+d10+2d8+d6
+32: d20+2d10+d8+d6
+33: d20+2d10+2d8
+34: d20+3d10+d8
+35: d20+d12+2d10+d8
+";
+
+/// Parse `step({step})` and roll the dice that the step translates to.
+pub fn main(state: &mut State<impl Rng>) -> PartialResult {
+    let step = super::expression(state)?;
+    let dice = state.variables.steps().get(&step);
+    if state.skipping {
+        return state.checked(dice.map(|_| Value::zero()), Value::zero);
+    }
+    let dice = dice?;
+    write!(state.output, ": {dice} ")?;
+    super::roll::step(state, &dice)
+}
+
+/// Table translating step numbers into dice, such as step 9 into `d8+d6`.
+#[derive(Debug, Clone)]
+pub struct Steps {
+    dice: Box<[Dice]>,
+    /// Whether steps past the end of the table are a d20 added to the step 11 lower, as in Earthdawn.
+    extend: bool,
+}
+
+impl Default for Steps {
+    fn default() -> Self {
+        Self {
+            extend: true,
+            ..Self::parse(EARTHDAWN).expect("built-in step table should be well formed")
+        }
+    }
+}
+
+impl Steps {
+    /// Parse a step table where each line is a step followed by its dice, such as `9: d8+d6`.
+    ///
+    /// Blank lines and anything following a `#` are ignored, and every step from 1 up to the greatest must be present.
+    pub fn parse(source: &str) -> Result<Self, ParseStepsError> {
+        let mut dice = Vec::<Option<Dice>>::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let malformed = ParseStepsError::Malformed { line: index + 1 };
+            let (step, step_dice) = line.split_once(':').ok_or(malformed)?;
+            let step = step
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|step| step.checked_sub(1))
+                .ok_or(malformed)?;
+            let step_dice = Dice::parse(step_dice.trim()).ok_or(malformed)?;
+            if dice.len() <= step {
+                dice.resize(step + 1, None);
+            }
+            if dice[step].replace(step_dice).is_some() {
+                return Err(ParseStepsError::Repeated(step + 1));
+            }
+        }
+        let dice = dice
+            .into_iter()
+            .enumerate()
+            .map(|(index, dice)| dice.ok_or(ParseStepsError::Missing(index + 1)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            dice,
+            extend: false,
+        })
+    }
+
+    fn get(&self, step: &Value) -> Result<Dice, EvalError> {
+        let undefined = || StepError::Undefined(step.clone());
+        let step = match step.to_natural() {
+            Ok(step) => step,
+            Err(crate::value::NaturalError::Type(error)) => return Err(error.into()),
+            Err(_) => return Err(undefined().into()),
+        };
+        let len = BigUint::from(self.dice.len());
+        let (d20s, step) = if step > len && self.extend {
+            let d20s = (&step - &len).div_ceil(&BigUint::from(11u32));
+            let step = step - &d20s * 11u32;
+            (d20s, step)
+        } else {
+            (BigUint::zero(), step)
+        };
+        let index = step
+            .to_usize()
+            .and_then(|step| step.checked_sub(1))
+            .ok_or_else(undefined)?;
+        let mut dice = self.dice.get(index).ok_or_else(undefined)?.clone();
+        let d20s = d20s.to_usize().ok_or(StepError::ExceedsMaximum)?;
+        dice.sizes
+            .splice(0..0, std::iter::repeat_n(BigUint::from(20u32), d20s));
+        Ok(dice)
+    }
+}
+
+/// Dice and flat modifier that a step translates to.
+#[derive(Debug, Clone)]
+pub struct Dice {
+    /// Size of every die, from greatest to least.
+    pub sizes: Vec<BigUint>,
+    pub modifier: BigInt,
+}
+
+impl Dice {
+    /// Parse dice such as `d20+2d6` or `d4-2`.
+    fn parse(source: &str) -> Option<Self> {
+        let mut sizes = Vec::new();
+        let mut modifier = BigInt::zero();
+        let source = source.replace('-', "+-");
+        for (index, term) in source.split('+').enumerate() {
+            let term = term.trim();
+            // a leading `-` leaves an empty first term
+            if term.is_empty() && index == 0 {
+                continue;
+            }
+            match term.split_once('d') {
+                Some((count, size)) => {
+                    let count = match count {
+                        "" => 1,
+                        count => count.parse::<usize>().ok()?,
+                    };
+                    let size = size.parse::<BigUint>().ok()?;
+                    if size.is_zero() {
+                        return None;
+                    }
+                    sizes.extend(std::iter::repeat_n(size, count));
+                }
+                None => modifier += term.parse::<BigInt>().ok()?,
+            }
+        }
+        sizes.sort_by(|a, b| b.cmp(a));
+        Some(Self { sizes, modifier })
+    }
+}
+
+impl fmt::Display for Dice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { sizes, modifier } = self;
+
+        for (index, run) in sizes.chunk_by(|a, b| a == b).enumerate() {
+            if index != 0 {
+                write!(f, "+")?;
+            }
+            match run.len() {
+                1 => write!(f, "d{}", run[0])?,
+                len => write!(f, "{len}d{}", run[0])?,
+            }
+        }
+        match modifier.sign() {
+            num::bigint::Sign::Plus => write!(f, "+{modifier}"),
+            num::bigint::Sign::Minus => write!(f, "{modifier}"),
+            num::bigint::Sign::NoSign => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ParseStepsError {
+    Malformed { line: usize },
+    Missing(usize),
+    Repeated(usize),
+}
+
+impl fmt::Display for ParseStepsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { line } => write!(f, "malformed step on line {line}"),
+            Self::Missing(step) => write!(f, "missing step {step}"),
+            Self::Repeated(step) => write!(f, "repeated step {step}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseStepsError {}
+
+#[derive(Debug)]
+pub enum StepError {
+    Undefined(Value),
+    ExceedsMaximum,
+}
+
+impl StepError {
+    pub fn class(&self) -> crate::ErrorClass {
+        match self {
+            Self::Undefined(_) => crate::ErrorClass::Evaluation,
+            Self::ExceedsMaximum => crate::ErrorClass::Limit,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Undefined(_) => "undefined_step",
+            Self::ExceedsMaximum => "step_exceeds_maximum",
+        }
+    }
+}
+
+impl From<StepError> for EvalError {
+    fn from(error: StepError) -> Self {
+        Self::Step(error)
+    }
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(step) => write!(f, "undefined step {step}"),
+            Self::ExceedsMaximum => write!(f, "step exceeds maximum"),
+        }
+    }
+}
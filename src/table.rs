@@ -0,0 +1,440 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use num::{BigUint, One as _, Zero as _};
+use rand::Rng;
+use std::{collections::HashMap, fmt, io, path::Path};
+
+/// Greatest depth of tables rolling on other tables, which keeps tables that refer to themselves from recursing forever.
+const MAX_DEPTH: usize = 32;
+
+/// Parse `table({name})`, roll on the table and evaluate any expressions embedded in the chosen entry as `[[{expression}]]`.
+///
+/// The value is the face rolled on the table labeled with the entry, such as `4 (3 wolves)`.
+pub fn main(state: &mut State<impl Rng>) -> PartialResult {
+    let name = state
+        .lexer
+        .matching(Token::Identifier)
+        .ok_or(EvalError::UnexpectedToken)?;
+    state.output.push_str(name);
+    if state.skipping {
+        return Ok(Value::zero());
+    }
+    let variables = state.variables;
+    let table = variables
+        .tables()
+        .get(name)
+        .ok_or_else(|| TableError::Undefined(name.into()))?;
+    state.output.push_str(": ");
+    let face = super::roll::plain(state, Value::from(1), table.size.clone())?.remove(0);
+    state.output.push(' ');
+    let entry = table
+        .entries
+        .iter()
+        .find(|entry| entry.least <= face && face <= entry.greatest)
+        .expect("entries should cover every face");
+    let mut label = String::new();
+    let mut rest = &*entry.text;
+    while let Some((start, raw, end)) = super::template::inline(&entry.text, rest)? {
+        state.output.push_str(&rest[..start]);
+        label.push_str(&rest[..start]);
+        let (output, value) = embedded(state, raw)?;
+        state.output.push_str(&output);
+        // entries of nested tables stand in for them rather than their faces
+        match value {
+            Value::Labeled(_, inner) => label.push_str(&inner),
+            value => label.push_str(&value.to_string()),
+        }
+        rest = &rest[end..];
+    }
+    state.output.push_str(rest);
+    label.push_str(rest);
+    Ok(Value::Labeled(Box::new(Value::from(face)), label.into()))
+}
+
+/// Evaluate an expression embedded in an entry, such as `1d4` or `table(loot)`.
+fn embedded<R: Rng>(state: &mut State<R>, raw: &str) -> Result<(String, Value), EvalError> {
+    if state.depth >= MAX_DEPTH {
+        return Err(TableError::ExceedsMaximumDepth.into());
+    }
+    let super::Evaluation {
+        output,
+        value,
+        pools,
+        ..
+    } = super::evaluate_at(
+        raw,
+        state.variables,
+        &mut *state.decks,
+        &mut *state.rng,
+        state.depth + 1,
+        None,
+    )?;
+    state.pools.extend(pools);
+    Ok((output, value))
+}
+
+/// Tables that `table` rolls on by name.
+#[derive(Debug, Clone, Default)]
+pub struct Tables {
+    tables: HashMap<Box<str>, Table>,
+}
+
+impl Tables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every `.txt` file in a directory as a table named after the file, such as `encounters.txt` as `encounters`.
+    ///
+    /// Files must be named like variables, so `level1.txt` is an error rather than a table that can't be rolled on.
+    pub fn load(directory: impl AsRef<Path>) -> Result<Self, LoadTablesError> {
+        let mut tables = Self::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "txt") {
+                continue;
+            }
+            let Some(name) = path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .filter(|name| crate::Variables::is_name(name))
+            else {
+                return Err(LoadTablesError::Name(path.display().to_string().into()));
+            };
+            let source = std::fs::read_to_string(&path)?;
+            let table = Table::parse(&source).map_err(|error| LoadTablesError::Parse {
+                name: name.into(),
+                error,
+            })?;
+            tables.insert(name, table);
+        }
+        Ok(tables)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Table> {
+        self.tables.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, table: Table) -> Option<Table> {
+        self.tables.insert(name.into(), table)
+    }
+}
+
+/// Entries that a die is rolled against, each covering a range of its faces.
+#[derive(Debug, Clone)]
+pub struct Table {
+    entries: Box<[Entry]>,
+    size: BigUint,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    least: BigUint,
+    greatest: BigUint,
+    text: Box<str>,
+}
+
+impl Table {
+    /// Parse a table where each line is an entry preceded by the faces it covers, such as `1-3: goblin` or `6: [[1d4]] wolves`, or by its weight, such as `3x: goblin`.
+    ///
+    /// Faces must cover every number from one up to the greatest exactly once, whereas weights are relative to the others.
+    /// Blank lines and anything following a `#` are ignored.
+    pub fn parse(source: &str) -> Result<Self, ParseTableError> {
+        let mut ranges = Vec::new();
+        let mut weights = Vec::new();
+        for (index, line) in source.lines().enumerate() {
+            let line = line.split_once('#').map_or(line, |(line, _)| line);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let malformed = || ParseTableError::Malformed { line: index + 1 };
+            let (key, text) = line.split_once(':').ok_or_else(malformed)?;
+            let (key, text) = (key.trim(), text.trim());
+            // embedded expressions must be terminated, though they're only evaluated when rolled
+            let mut rest = text;
+            while let Some((start, _, end)) =
+                crate::template::inline(text, rest).map_err(|_| malformed())?
+            {
+                if is_bare(&rest[..start]) {
+                    return Err(ParseTableError::Bare { line: index + 1 });
+                }
+                rest = &rest[end..];
+            }
+            if is_bare(rest) {
+                return Err(ParseTableError::Bare { line: index + 1 });
+            }
+            let text = text.into();
+            if let Some(weight) = key.strip_suffix('x') {
+                let weight = weight.parse::<BigUint>().map_err(|_| malformed())?;
+                weights.push((weight, text));
+            } else {
+                let (least, greatest) = key.split_once('-').unwrap_or((key, key));
+                let least = least.trim().parse::<BigUint>().map_err(|_| malformed())?;
+                let greatest = greatest
+                    .trim()
+                    .parse::<BigUint>()
+                    .map_err(|_| malformed())?;
+                if least > greatest {
+                    return Err(malformed());
+                }
+                ranges.push(Entry {
+                    least,
+                    greatest,
+                    text,
+                });
+            }
+            if !ranges.is_empty() && !weights.is_empty() {
+                return Err(ParseTableError::Mixed { line: index + 1 });
+            }
+        }
+        let mut entries = if ranges.is_empty() {
+            let mut least = BigUint::one();
+            weights
+                .into_iter()
+                .filter(|(weight, _)| !weight.is_zero())
+                .map(|(weight, text)| {
+                    let greatest = &least + weight - 1u32;
+                    let next = &greatest + 1u32;
+                    Entry {
+                        least: std::mem::replace(&mut least, next),
+                        greatest,
+                        text,
+                    }
+                })
+                .collect::<Vec<_>>()
+        } else {
+            ranges
+        };
+        entries.sort_by(|a, b| a.least.cmp(&b.least));
+        let mut next = BigUint::one();
+        for entry in &entries {
+            if entry.least < next {
+                return Err(ParseTableError::Overlapping(entry.least.clone()));
+            }
+            if entry.least > next {
+                return Err(ParseTableError::Uncovered(next));
+            }
+            next = &entry.greatest + 1u32;
+        }
+        if entries.is_empty() {
+            return Err(ParseTableError::Empty);
+        }
+        Ok(Self {
+            entries: entries.into_boxed_slice(),
+            size: next - 1u32,
+        })
+    }
+}
+
+/// Whether text outside of `[[` and `]]` looks like it rolls dice or on a table, such as `1d4 wolves` or `table(loot)`, which would otherwise be left as text.
+fn is_bare(text: &str) -> bool {
+    let chars = text.chars().collect::<Vec<_>>();
+    let dice = (0..chars.len()).any(|index| {
+        let before = index.checked_sub(1).map(|before| chars[before]);
+        chars[index] == 'd'
+            && chars.get(index + 1).is_some_and(char::is_ascii_digit)
+            && before.is_none_or(|before| before.is_ascii_digit() || !before.is_alphanumeric())
+    });
+    dice || text.contains("table(")
+}
+
+#[derive(Debug, Clone)]
+pub enum ParseTableError {
+    Malformed {
+        line: usize,
+    },
+    /// Faces and weights are both used.
+    Mixed {
+        line: usize,
+    },
+    /// Dice or a table are written outside of `[[` and `]]`.
+    Bare {
+        line: usize,
+    },
+    Empty,
+    Overlapping(BigUint),
+    Uncovered(BigUint),
+}
+
+impl fmt::Display for ParseTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed { line } => write!(f, "malformed entry on line {line}"),
+            Self::Mixed { line } => write!(f, "faces and weights mixed on line {line}"),
+            Self::Bare { line } => write!(
+                f,
+                "dice and tables on line {line} must be embedded between `[[` and `]]`"
+            ),
+            Self::Empty => write!(f, "table has no entries"),
+            Self::Overlapping(face) => write!(f, "face {face} is covered more than once"),
+            Self::Uncovered(face) => write!(f, "face {face} is not covered"),
+        }
+    }
+}
+
+impl std::error::Error for ParseTableError {}
+
+#[derive(Debug)]
+pub enum LoadTablesError {
+    Io(io::Error),
+    /// Path of a file that isn't named like a variable.
+    Name(Box<str>),
+    Parse {
+        name: Box<str>,
+        error: ParseTableError,
+    },
+}
+
+impl From<io::Error> for LoadTablesError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl fmt::Display for LoadTablesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{error}"),
+            Self::Name(path) => write!(
+                f,
+                "table file `{path}` must be named with only letters and underscores"
+            ),
+            Self::Parse { name, error } => write!(f, "parsing table `{name}`: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadTablesError {}
+
+#[derive(Debug)]
+pub enum TableError {
+    Undefined(Box<str>),
+    ExceedsMaximumDepth,
+}
+
+impl TableError {
+    pub fn class(&self) -> crate::ErrorClass {
+        match self {
+            Self::Undefined(_) => crate::ErrorClass::Evaluation,
+            Self::ExceedsMaximumDepth => crate::ErrorClass::Limit,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Undefined(_) => "undefined_table",
+            Self::ExceedsMaximumDepth => "table_exceeds_maximum_depth",
+        }
+    }
+}
+
+impl From<TableError> for EvalError {
+    fn from(error: TableError) -> Self {
+        Self::Table(error)
+    }
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Undefined(name) => write!(f, "undefined table `{name}`"),
+            Self::ExceedsMaximumDepth => write!(f, "tables nest too deeply"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(table: &Table) -> Vec<(u32, u32, &str)> {
+        table
+            .entries
+            .iter()
+            .map(|entry| {
+                let least = u32::try_from(&entry.least).unwrap();
+                let greatest = u32::try_from(&entry.greatest).unwrap();
+                (least, greatest, &*entry.text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn ranges() {
+        let table = Table::parse("4-6: [[1d4]] wolves # pack\n\n1-3: goblin\n").unwrap();
+        assert_eq!(
+            entries(&table),
+            [(1, 3, "goblin"), (4, 6, "[[1d4]] wolves")]
+        );
+        assert_eq!(table.size, BigUint::from(6u32));
+    }
+
+    #[test]
+    fn weights() {
+        let table = Table::parse("3x: gold\n0x: nothing\n1x: sword").unwrap();
+        assert_eq!(entries(&table), [(1, 3, "gold"), (4, 4, "sword")]);
+        assert_eq!(table.size, BigUint::from(4u32));
+    }
+
+    #[test]
+    fn overlapping() {
+        assert!(matches!(
+            Table::parse("1-3: goblin\n3-4: orc"),
+            Err(ParseTableError::Overlapping(face)) if face == BigUint::from(3u32)
+        ));
+    }
+
+    #[test]
+    fn uncovered() {
+        assert!(matches!(
+            Table::parse("1-2: goblin\n4: orc"),
+            Err(ParseTableError::Uncovered(face)) if face == BigUint::from(3u32)
+        ));
+        assert!(matches!(
+            Table::parse("2: goblin"),
+            Err(ParseTableError::Uncovered(face)) if face == BigUint::one()
+        ));
+    }
+
+    #[test]
+    fn mixed() {
+        assert!(matches!(
+            Table::parse("1: goblin\n2x: orc"),
+            Err(ParseTableError::Mixed { line: 2 })
+        ));
+    }
+
+    #[test]
+    fn bare() {
+        for source in [
+            "1: 1d4 wolves",
+            "1: d20 rats",
+            "1: [[1d4]] wolves and (2d6) rats",
+            "1: table(loot)",
+        ] {
+            assert!(
+                matches!(Table::parse(source), Err(ParseTableError::Bare { line: 1 })),
+                "{source:?}"
+            );
+        }
+        assert!(Table::parse("1: the 3rd door, dd2 and [[1d4]] wolves").is_ok());
+    }
+
+    #[test]
+    fn malformed() {
+        for (source, line) in [
+            ("goblin", 1),
+            ("1: goblin\n3-2: orc", 2),
+            ("x: goblin", 1),
+            ("1: [[1d6 wolves", 1),
+        ] {
+            assert!(
+                matches!(Table::parse(source), Err(ParseTableError::Malformed { line: found }) if found == line),
+                "{source:?}"
+            );
+        }
+        assert!(matches!(
+            Table::parse("# nothing\n"),
+            Err(ParseTableError::Empty)
+        ));
+    }
+}
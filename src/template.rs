@@ -0,0 +1,102 @@
+use super::{Decks, EvalError, Variables};
+use rand::Rng;
+use std::fmt;
+
+/// How inline expressions are replaced within a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateStyle {
+    /// Replace each expression with its value alone, such as `120`.
+    Values,
+    /// Replace each expression with its rolls and value in brackets, such as `[[4 3 5]*10 = 120]`.
+    Breakdowns,
+}
+
+/// Replace every `[[{expression}]]` within text with its result, such as `The chest holds [[3d6*10]] gold`.
+///
+/// Expressions are evaluated in order, so they draw from `decks` one after another, but no cards are drawn unless every expression succeeds.
+pub fn main(
+    raw: &str,
+    variables: &Variables,
+    decks: &mut Decks,
+    style: TemplateStyle,
+    rng: &mut impl Rng,
+) -> Result<String, EvalError> {
+    let mut drawing = decks.clone();
+    let mut rendered = String::new();
+    let mut rest = raw;
+    while let Some((start, inline, end)) = inline(raw, rest)? {
+        rendered.push_str(&rest[..start]);
+        let evaluation = super::evaluate_with_decks(inline, variables, &mut drawing, rng)?;
+        match style {
+            TemplateStyle::Values => rendered.push_str(&evaluation.value().to_string()),
+            TemplateStyle::Breakdowns => rendered.push_str(&format!("[{evaluation}]")),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+    *decks = drawing;
+    Ok(rendered)
+}
+
+/// Find the first `[[{expression}]]` within the `rest` of `raw`, returning where it starts, the expression and where it ends.
+///
+/// Brackets within the expression, such as those of `1d6[fire]`, are balanced and anything quoted is skipped.
+pub(crate) fn inline<'r>(
+    raw: &str,
+    rest: &'r str,
+) -> Result<Option<(usize, &'r str, usize)>, TemplateError> {
+    let Some(start) = rest.find("[[") else {
+        return Ok(None);
+    };
+    let inline = &rest[start + 2..];
+    let mut depth = 0usize;
+    let mut quoted = false;
+    let mut chars = inline.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            _ if quoted => {}
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            ']' if chars.peek().is_some_and(|&(_, c)| c == ']') => {
+                return Ok(Some((start, &inline[..index], start + 2 + index + 2)));
+            }
+            _ => {}
+        }
+    }
+    Err(TemplateError::Unterminated {
+        offset: raw.len() - rest.len() + start,
+    })
+}
+
+#[derive(Debug)]
+pub enum TemplateError {
+    Unterminated { offset: usize },
+}
+
+impl TemplateError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Unterminated { .. } => "unterminated_inline_expression",
+        }
+    }
+}
+
+impl From<TemplateError> for EvalError {
+    fn from(error: TemplateError) -> Self {
+        Self::Template(error)
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unterminated { offset } => {
+                write!(
+                    f,
+                    "inline expression at offset {offset} is missing its `]]`"
+                )
+            }
+        }
+    }
+}
@@ -1,13 +1,188 @@
-use crate::EvalError;
+use crate::{json::Json, EvalError};
 use num::{BigInt, BigRational, BigUint, One as _, Zero as _};
-use std::{convert::Infallible, fmt};
+use std::fmt;
 
-#[derive(Debug)]
-pub struct Value(BigRational);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Number(BigRational),
+    Bool(bool),
+    List(Box<[Value]>),
+    Record(Fields),
+    Symbols(Symbols),
+    /// Value classified by an outcome table, which behaves like the value itself.
+    Labeled(Box<Value>, Box<str>),
+    Damage(Damage),
+}
+
+/// Number made up of amounts of damage of different types, which behaves like its total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Damage {
+    total: BigRational,
+    /// Amount of each type of damage in the order that they first appear, where untyped damage has an empty type.
+    types: Box<[(Box<str>, BigRational)]>,
+}
+
+impl Damage {
+    pub fn new(ty: &str, amount: BigRational) -> Self {
+        Self {
+            total: amount.clone(),
+            types: Box::new([(ty.into(), amount)]),
+        }
+    }
+
+    fn from_types(types: Vec<(Box<str>, BigRational)>) -> Self {
+        Self {
+            total: types.iter().map(|(_, amount)| amount).sum(),
+            types: types.into_boxed_slice(),
+        }
+    }
+
+    /// Replace the amount of every type of damage.
+    pub fn scale(self, scale: impl Fn(&str, BigRational) -> BigRational) -> Self {
+        Self::from_types(
+            self.types
+                .into_vec()
+                .into_iter()
+                .map(|(ty, amount)| {
+                    let amount = scale(&ty, amount);
+                    (ty, amount)
+                })
+                .collect(),
+        )
+    }
+
+    fn to_fields(&self) -> Fields {
+        self.types
+            .iter()
+            .map(|(ty, amount)| {
+                let ty = if ty.is_empty() { "untyped" } else { ty };
+                (ty.into(), Value::from(amount.clone()))
+            })
+            .collect()
+    }
+}
+
+impl std::ops::Add for Damage {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        let mut types = self.types.into_vec();
+        for (ty, amount) in rhs.types.into_vec() {
+            match types.iter_mut().find(|(existing, _)| *existing == ty) {
+                Some((_, existing)) => *existing += amount,
+                None => types.push((ty, amount)),
+            }
+        }
+        Self::from_types(types)
+    }
+}
+
+impl fmt::Display for Damage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { total, types } = self;
+
+        write!(f, "{} (", to_f64(total))?;
+        for (index, (ty, amount)) in types.iter().enumerate() {
+            if index != 0 {
+                write!(f, ", ")?;
+            }
+            match &**ty {
+                "" => write!(f, "{} untyped", to_f64(amount))?,
+                ty => write!(f, "{} {ty}", to_f64(amount))?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// Named fields of a record in the order they were defined.
+pub type Fields = Box<[(Box<str>, Value)]>;
+
+/// Net symbols of narrative dice.
+///
+/// Failures are negative successes and threats are negative advantages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    pub success: BigInt,
+    pub advantage: BigInt,
+    pub triumph: BigUint,
+    pub despair: BigUint,
+}
+
+impl Symbols {
+    fn to_fields(&self) -> Fields {
+        let Self {
+            success,
+            advantage,
+            triumph,
+            despair,
+        } = self;
+
+        [
+            ("success", Value::from(success.clone())),
+            ("advantage", Value::from(advantage.clone())),
+            ("triumph", Value::from(triumph.clone())),
+            ("despair", Value::from(despair.clone())),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.into(), value))
+        .collect()
+    }
+}
+
+impl std::ops::Add for Symbols {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            success: self.success + rhs.success,
+            advantage: self.advantage + rhs.advantage,
+            triumph: self.triumph + rhs.triumph,
+            despair: self.despair + rhs.despair,
+        }
+    }
+}
+
+impl fmt::Display for Symbols {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use num::Signed as _;
+
+        let Self {
+            success,
+            advantage,
+            triumph,
+            despair,
+        } = self;
+
+        let success = match success.is_negative() {
+            true => (success.abs(), "failure"),
+            false => (success.clone(), "success"),
+        };
+        let advantage = match advantage.is_negative() {
+            true => (advantage.abs(), "threat"),
+            false => (advantage.clone(), "advantage"),
+        };
+        let symbols = [
+            success,
+            advantage,
+            (BigInt::from(triumph.clone()), "triumph"),
+            (BigInt::from(despair.clone()), "despair"),
+        ];
+        let mut symbols = symbols.iter().filter(|(amount, _)| !amount.is_zero());
+        match symbols.next() {
+            Some((amount, name)) => write!(f, "{amount} {name}")?,
+            None => return write!(f, "blank"),
+        }
+        for (amount, name) in symbols {
+            write!(f, ", {amount} {name}")?;
+        }
+        Ok(())
+    }
+}
 
 impl From<BigRational> for Value {
     fn from(value: BigRational) -> Self {
-        Self(value)
+        Self::Number(value)
     }
 }
 
@@ -23,99 +198,347 @@ impl From<BigUint> for Value {
     }
 }
 
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Self::from(BigUint::from(value))
+    }
+}
+
 impl From<bool> for Value {
     fn from(b: bool) -> Self {
-        if b {
-            Value(BigRational::one())
-        } else {
-            Value(BigRational::zero())
-        }
+        Self::Bool(b)
+    }
+}
+
+impl FromIterator<Value> for Value {
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Self::List(iter.into_iter().collect())
     }
 }
 
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use num::ToPrimitive as _;
-
-        let Self(inner) = self;
-
-        let approx = inner
-            .to_f64()
-            .expect("value should be representable as an `f64`");
-        write!(f, "{approx}",)
+        match self {
+            Self::Number(number) => write!(f, "{}", to_f64(number)),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::List(elements) => {
+                write!(f, "[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{element}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Record(fields) => {
+                write!(f, "{{")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                write!(f, "}}")
+            }
+            Self::Symbols(symbols) => write!(f, "{symbols}"),
+            Self::Labeled(value, label) => write!(f, "{value} ({label})"),
+            Self::Damage(damage) => write!(f, "{damage}"),
+        }
     }
 }
 
 impl Value {
-    fn into_inner(self) -> BigRational {
-        let Self(inner) = self;
-        inner
+    pub fn ty(&self) -> Type {
+        match self {
+            Self::Number(_) => Type::Number,
+            Self::Bool(_) => Type::Bool,
+            Self::List(_) => Type::List,
+            Self::Record(_) => Type::Record,
+            Self::Symbols(_) => Type::Symbols,
+            Self::Labeled(value, _) => value.ty(),
+            Self::Damage(_) => Type::Number,
+        }
     }
 
-    fn as_inner(&self) -> &BigRational {
-        let Self(inner) = self;
-        inner
+    /// Value without the label of its outcome, if any.
+    pub(crate) fn unlabeled(self) -> Self {
+        match self {
+            Self::Labeled(value, _) => value.unlabeled(),
+            value => value,
+        }
     }
 
-    fn is_zero(&self) -> bool {
-        self.as_inner().is_zero()
+    pub(crate) fn into_number(self) -> Result<BigRational, TypeError> {
+        match self.unlabeled() {
+            Self::Number(number) => Ok(number),
+            Self::Damage(damage) => Ok(damage.total),
+            value => Err(TypeError::new(Type::Number, &value)),
+        }
     }
 
-    pub fn eq(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() == rhs.into_inner()))
+    pub(crate) fn as_number(&self) -> Result<&BigRational, TypeError> {
+        match self {
+            Self::Number(number) => Ok(number),
+            Self::Labeled(value, _) => value.as_number(),
+            Self::Damage(damage) => Ok(&damage.total),
+            _ => Err(TypeError::new(Type::Number, self)),
+        }
     }
 
-    pub fn ne(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() != rhs.into_inner()))
+    pub(crate) fn into_list(self) -> Result<Box<[Value]>, TypeError> {
+        match self.unlabeled() {
+            Self::List(elements) => Ok(elements),
+            value => Err(TypeError::new(Type::List, &value)),
+        }
     }
 
-    pub fn lt(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() < rhs.into_inner()))
+    pub fn record<const LEN: usize>(fields: [(&str, Value); LEN]) -> Self {
+        Self::Record(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.into(), value))
+                .collect(),
+        )
     }
 
-    pub fn le(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() <= rhs.into_inner()))
+    pub fn zero() -> Self {
+        Self::Number(BigRational::zero())
     }
 
-    pub fn gt(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() > rhs.into_inner()))
+    /// Whether the value is true, a non-zero number, a record of a `success` or symbols with a net success.
+    pub fn is_true(&self) -> bool {
+        match self {
+            Self::Number(number) => !number.is_zero(),
+            Self::Bool(b) => *b,
+            Self::List(_) => false,
+            Self::Record(fields) => fields
+                .iter()
+                .any(|(name, value)| &**name == "success" && value.is_true()),
+            Self::Symbols(symbols) => symbols.success > BigInt::zero(),
+            Self::Labeled(value, _) => value.is_true(),
+            Self::Damage(damage) => !damage.total.is_zero(),
+        }
     }
 
-    pub fn ge(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() >= rhs.into_inner()))
+    /// Fields of a record, or of symbols or damage which can be accessed like a record.
+    pub(crate) fn into_record(self) -> Result<Fields, TypeError> {
+        match self.unlabeled() {
+            Self::Record(fields) => Ok(fields),
+            Self::Symbols(symbols) => Ok(symbols.to_fields()),
+            Self::Damage(damage) => Ok(damage.to_fields()),
+            value => Err(TypeError::new(Type::Record, &value)),
+        }
     }
 
-    pub fn add(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() + rhs.into_inner()))
+    pub(crate) fn truth(&self) -> Result<bool, TypeError> {
+        match self {
+            Self::Bool(b) => Ok(*b),
+            Self::Labeled(value, _) => value.truth(),
+            _ => Err(TypeError::new(Type::Bool, self)),
+        }
     }
 
-    pub fn sub(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() - rhs.into_inner()))
+    fn check_same_type(&self, rhs: &Self) -> Result<(), TypeError> {
+        if self.ty() != rhs.ty() {
+            return Err(TypeError::new(self.ty(), rhs));
+        }
+        Ok(())
     }
 
-    pub fn mul(self, rhs: Self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.into_inner() * rhs.into_inner()))
+    pub fn to_natural(&self) -> Result<BigUint, NaturalError> {
+        let number = self.as_number()?;
+        if !number.is_integer() {
+            return Err(NaturalError::Fractional);
+        }
+        number
+            .to_integer()
+            .try_into()
+            .map_err(|_| NaturalError::Negative)
     }
 
-    pub fn div(self, rhs: Self) -> Result<Self, DivisionByZeroError> {
+    pub fn to_json(&self) -> Json {
+        let ty = Json::from(self.ty().to_string());
+        match self {
+            Self::Number(number) => Json::object([
+                ("type", ty),
+                ("exact", Json::from(number.to_string())),
+                ("approx", Json::from(to_f64(number))),
+            ]),
+            Self::Bool(b) => Json::object([("type", ty), ("bool", Json::from(*b))]),
+            Self::List(elements) => Json::object([
+                ("type", ty),
+                ("elements", elements.iter().map(Self::to_json).collect()),
+            ]),
+            Self::Record(fields) => {
+                Json::object([("type", ty), ("fields", fields_to_json(fields))])
+            }
+            Self::Symbols(symbols) => Json::object([
+                ("type", ty),
+                ("fields", fields_to_json(&symbols.to_fields())),
+            ]),
+            Self::Labeled(value, label) => {
+                let mut json = value.to_json();
+                if let Json::Object(fields) = &mut json {
+                    fields.push(("label".into(), Json::from(&**label)));
+                }
+                json
+            }
+            Self::Damage(damage) => Json::object([
+                ("type", ty),
+                ("exact", Json::from(damage.total.to_string())),
+                ("approx", Json::from(to_f64(&damage.total))),
+                ("damage", fields_to_json(&damage.to_fields())),
+            ]),
+        }
+    }
+
+    pub(crate) fn eq(self, rhs: Self) -> Result<Self, ValueError> {
+        self.check_same_type(&rhs)?;
+        Ok(Self::from(self.comparable() == rhs.comparable()))
+    }
+
+    pub(crate) fn ne(self, rhs: Self) -> Result<Self, ValueError> {
+        self.check_same_type(&rhs)?;
+        Ok(Self::from(self.comparable() != rhs.comparable()))
+    }
+
+    /// Value without a label or types of damage, which don't affect equality.
+    fn comparable(self) -> Self {
+        match self.unlabeled() {
+            Self::Damage(damage) => Self::Number(damage.total),
+            value => value,
+        }
+    }
+
+    /// Damage of a value, where plain numbers are untyped damage.
+    fn into_damage(self) -> Result<Damage, TypeError> {
+        match self.unlabeled() {
+            Self::Damage(damage) => Ok(damage),
+            value => Ok(Damage::new("", value.into_number()?)),
+        }
+    }
+
+    pub(crate) fn lt(self, rhs: Self) -> Result<Self, ValueError> {
+        Ok(Self::from(self.into_number()? < rhs.into_number()?))
+    }
+
+    pub(crate) fn le(self, rhs: Self) -> Result<Self, ValueError> {
+        Ok(Self::from(self.into_number()? <= rhs.into_number()?))
+    }
+
+    pub(crate) fn gt(self, rhs: Self) -> Result<Self, ValueError> {
+        Ok(Self::from(self.into_number()? > rhs.into_number()?))
+    }
+
+    pub(crate) fn ge(self, rhs: Self) -> Result<Self, ValueError> {
+        Ok(Self::from(self.into_number()? >= rhs.into_number()?))
+    }
+
+    /// Add numbers, or pool the symbols of narrative dice.
+    pub(crate) fn add(self, rhs: Self) -> Result<Self, ValueError> {
+        match (self.unlabeled(), rhs.unlabeled()) {
+            (Self::Symbols(lhs), Self::Symbols(rhs)) => Ok(Self::Symbols(lhs + rhs)),
+            (lhs @ Self::Damage(_), rhs) | (lhs, rhs @ Self::Damage(_)) => {
+                Ok(Self::Damage(lhs.into_damage()? + rhs.into_damage()?))
+            }
+            (lhs, rhs) => Ok(Self::from(lhs.into_number()? + rhs.into_number()?)),
+        }
+    }
+
+    /// Subtract numbers, where subtracting from or with damage keeps its types.
+    pub(crate) fn sub(self, rhs: Self) -> Result<Self, ValueError> {
+        match (self.unlabeled(), rhs.unlabeled()) {
+            (lhs @ Self::Damage(_), rhs) | (lhs, rhs @ Self::Damage(_)) => {
+                let rhs = rhs.into_damage()?.scale(|_, amount| -amount);
+                Ok(Self::Damage(lhs.into_damage()? + rhs))
+            }
+            (lhs, rhs) => Ok(Self::from(lhs.into_number()? - rhs.into_number()?)),
+        }
+    }
+
+    /// Multiply numbers, where damage is multiplied for every type.
+    pub(crate) fn mul(self, rhs: Self) -> Result<Self, ValueError> {
+        match (self.unlabeled(), rhs.unlabeled()) {
+            (Self::Damage(damage), factor) | (factor, Self::Damage(damage)) => {
+                let factor = factor.into_number()?;
+                Ok(Self::Damage(damage.scale(|_, amount| amount * &factor)))
+            }
+            (lhs, rhs) => Ok(Self::from(lhs.into_number()? * rhs.into_number()?)),
+        }
+    }
+
+    pub(crate) fn div(self, rhs: Self) -> Result<Self, ValueError> {
+        let (lhs, rhs) = (self.into_number()?, rhs.into_number()?);
         if rhs.is_zero() {
-            return Err(DivisionByZeroError);
+            return Err(DivisionByZeroError.into());
         }
-        Ok(Self::from(self.into_inner() / rhs.into_inner()))
+        Ok(Self::from(lhs / rhs))
     }
 
-    pub fn not(self) -> Result<Self, Infallible> {
-        Ok(Self::from(self.is_zero()))
+    pub(crate) fn not(self) -> Result<Self, ValueError> {
+        Ok(Self::from(!self.truth()?))
     }
 
-    pub fn neg(self) -> Result<Self, Infallible> {
-        Ok(Self::from(-self.into_inner()))
+    pub(crate) fn neg(self) -> Result<Self, ValueError> {
+        match self.unlabeled() {
+            Self::Damage(damage) => Ok(Self::Damage(damage.scale(|_, amount| -amount))),
+            value => Ok(Self::from(-value.into_number()?)),
+        }
+    }
+}
+
+fn fields_to_json(fields: &[(Box<str>, Value)]) -> Json {
+    Json::Object(
+        fields
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_json()))
+            .collect(),
+    )
+}
+
+pub(crate) fn to_f64(number: &BigRational) -> f64 {
+    use num::ToPrimitive as _;
+
+    number
+        .to_f64()
+        .expect("value should be representable as an `f64`")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Number,
+    Bool,
+    List,
+    Record,
+    Symbols,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number => write!(f, "number"),
+            Self::Bool => write!(f, "bool"),
+            Self::List => write!(f, "list"),
+            Self::Record => write!(f, "record"),
+            Self::Symbols => write!(f, "symbols"),
+        }
     }
 }
 
 #[derive(Debug)]
 pub enum ValueError {
     DivisionByZero(DivisionByZeroError),
+    Type(TypeError),
+}
+
+impl ValueError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DivisionByZero(_) => "division_by_zero",
+            Self::Type(_) => "type",
+        }
+    }
 }
 
 impl From<ValueError> for EvalError {
@@ -128,6 +551,7 @@ impl fmt::Display for ValueError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::DivisionByZero(error) => write!(f, "{error}"),
+            Self::Type(error) => write!(f, "{error}"),
         }
     }
 }
@@ -144,6 +568,18 @@ impl From<DivisionByZeroError> for EvalError {
     }
 }
 
+impl From<TypeError> for ValueError {
+    fn from(error: TypeError) -> Self {
+        Self::Type(error)
+    }
+}
+
+impl From<TypeError> for EvalError {
+    fn from(error: TypeError) -> Self {
+        Self::Value(ValueError::from(error))
+    }
+}
+
 #[derive(Debug)]
 pub struct DivisionByZeroError;
 
@@ -152,3 +588,39 @@ impl fmt::Display for DivisionByZeroError {
         write!(f, "division by zero")
     }
 }
+
+#[derive(Debug)]
+pub struct TypeError {
+    pub expected: Type,
+    pub found: Type,
+}
+
+impl TypeError {
+    pub fn new(expected: Type, found: &Value) -> Self {
+        Self {
+            expected,
+            found: found.ty(),
+        }
+    }
+}
+
+impl fmt::Display for TypeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { expected, found } = self;
+
+        write!(f, "expected {expected}, found {found}")
+    }
+}
+
+#[derive(Debug)]
+pub enum NaturalError {
+    Negative,
+    Fractional,
+    Type(TypeError),
+}
+
+impl From<TypeError> for NaturalError {
+    fn from(error: TypeError) -> Self {
+        Self::Type(error)
+    }
+}
@@ -0,0 +1,57 @@
+use super::{EvalError, Steps, Tables, Token, Value};
+use logos::Logos as _;
+use std::collections::HashMap;
+
+pub fn main(state: &mut super::State<impl rand::Rng>, name: &str) -> Result<Value, EvalError> {
+    state.output.push_str(name);
+    match state.variables.get(name) {
+        Some(value) => Ok(value.clone()),
+        None if state.skipping => Ok(Value::zero()),
+        None => Err(EvalError::UndefinedVariable(name.into())),
+    }
+}
+
+/// Named values that expressions can refer to, along with the step table used by `step` and the tables used by `table`.
+#[derive(Debug, Clone, Default)]
+pub struct Variables {
+    values: HashMap<Box<str>, Value>,
+    steps: Steps,
+    tables: Tables,
+}
+
+impl Variables {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `name` can be referred to from an expression.
+    pub fn is_name(name: &str) -> bool {
+        let mut lexer = Token::lexer(name);
+        lexer.next() == Some(Ok(Token::Identifier)) && lexer.next().is_none()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    pub fn insert(&mut self, name: &str, value: Value) -> Option<Value> {
+        self.values.insert(name.into(), value)
+    }
+
+    pub fn steps(&self) -> &Steps {
+        &self.steps
+    }
+
+    /// Replace the Earthdawn step table with that of another edition or game.
+    pub fn set_steps(&mut self, steps: Steps) {
+        self.steps = steps;
+    }
+
+    pub fn tables(&self) -> &Tables {
+        &self.tables
+    }
+
+    pub fn tables_mut(&mut self) -> &mut Tables {
+        &mut self.tables
+    }
+}
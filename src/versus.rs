@@ -0,0 +1,237 @@
+use super::{EvalError, PartialResult, State, Token, Value};
+use num::{BigRational, BigUint, One as _, Signed as _, Zero as _};
+use rand::Rng;
+use std::fmt;
+
+/// Parse `{total} vs {target} {rule}?`, producing an outcome labeled with its degree of success.
+///
+/// The outcome is a record of whether the total is a `success` and its `margin` over the target along with any fields of its rule.
+pub fn main(state: &mut State<impl Rng>) -> PartialResult {
+    let start = state.pools.len();
+    let total = super::term(state)?;
+    if !state.lexer.matches(Token::Vs) {
+        return Ok(total);
+    }
+    // the natural die is that of the total, so it must be found before the target rolls anything
+    let natural = match &state.pools[start..] {
+        [pool] => pool
+            .natural()
+            .map(|(size, face)| (size.clone(), face.clone())),
+        _ => None,
+    };
+    state.output.push_str(" vs ");
+    let target = super::term(state)?;
+    let rule = match state.lexer.matching(Token::Identifier) {
+        Some(name) => {
+            state.output.push(' ');
+            state.output.push_str(name);
+            Some(Rule::from_name(name).ok_or_else(|| VersusError::UndefinedRule(name.into()))?)
+        }
+        None => None,
+    };
+    let margin = total.into_number()? - target.into_number()?;
+    let success = !margin.is_negative();
+    Ok(match rule {
+        None => Value::Labeled(
+            Box::new(Value::record([
+                ("success", Value::from(success)),
+                ("margin", Value::from(margin)),
+            ])),
+            if success { "success" } else { "failure" }.into(),
+        ),
+        Some(Rule::Pathfinder) => {
+            let degree = degree(&margin, natural.as_ref());
+            Value::Labeled(
+                Box::new(Value::record([
+                    ("success", Value::from(degree >= Degree::Success)),
+                    ("margin", Value::from(margin)),
+                    ("degree", Value::from(degree as usize)),
+                ])),
+                degree.to_string().into(),
+            )
+        }
+        Some(Rule::Raises) => {
+            let raises = raises(&margin);
+            let label = match success {
+                true if raises.is_one() => "success with 1 raise".into(),
+                true if !raises.is_zero() => format!("success with {raises} raises"),
+                true => "success".into(),
+                false => "failure".into(),
+            };
+            Value::Labeled(
+                Box::new(Value::record([
+                    ("success", Value::from(success)),
+                    ("margin", Value::from(margin)),
+                    ("raises", Value::from(raises)),
+                ])),
+                label.into(),
+            )
+        }
+    })
+}
+
+/// Number of whole steps of 4 by which a total meets its target, as counted by Savage Worlds.
+pub fn raises(margin: &BigRational) -> BigRational {
+    if margin.is_negative() {
+        return BigRational::zero();
+    }
+    (margin / BigRational::from_integer(4.into())).floor()
+}
+
+/// Degree of success in Pathfinder 2e, where a natural 20 or 1 on a d20 shifts the degree by one.
+fn degree(margin: &BigRational, natural: Option<&(BigUint, BigUint)>) -> Degree {
+    let ten = BigRational::from_integer(10.into());
+    let mut degree = if *margin >= ten {
+        Degree::CriticalSuccess
+    } else if !margin.is_negative() {
+        Degree::Success
+    } else if *margin > -ten {
+        Degree::Failure
+    } else {
+        Degree::CriticalFailure
+    };
+    let twenty = BigUint::from(20u32);
+    if let Some((_, face)) = natural.filter(|(size, _)| *size == twenty) {
+        if *face == twenty {
+            degree = degree.improved();
+        } else if face.is_one() {
+            degree = degree.worsened();
+        }
+    }
+    degree
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Degree {
+    CriticalFailure,
+    Failure,
+    Success,
+    CriticalSuccess,
+}
+
+impl Degree {
+    fn improved(self) -> Self {
+        match self {
+            Self::CriticalFailure => Self::Failure,
+            Self::Failure => Self::Success,
+            Self::Success | Self::CriticalSuccess => Self::CriticalSuccess,
+        }
+    }
+
+    fn worsened(self) -> Self {
+        match self {
+            Self::CriticalFailure | Self::Failure => Self::CriticalFailure,
+            Self::Success => Self::Failure,
+            Self::CriticalSuccess => Self::Success,
+        }
+    }
+}
+
+impl fmt::Display for Degree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CriticalFailure => write!(f, "critical failure"),
+            Self::Failure => write!(f, "failure"),
+            Self::Success => write!(f, "success"),
+            Self::CriticalSuccess => write!(f, "critical success"),
+        }
+    }
+}
+
+/// Rule deciding the degrees of success beyond success and failure.
+enum Rule {
+    Pathfinder,
+    Raises,
+}
+
+impl Rule {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pathfinder" => Some(Self::Pathfinder),
+            "raises" => Some(Self::Raises),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum VersusError {
+    UndefinedRule(Box<str>),
+}
+
+impl VersusError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::UndefinedRule(_) => "undefined_rule",
+        }
+    }
+}
+
+impl From<VersusError> for EvalError {
+    fn from(error: VersusError) -> Self {
+        Self::Versus(error)
+    }
+}
+
+impl fmt::Display for VersusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UndefinedRule(name) => write!(f, "undefined rule `{name}`"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pathfinder(margin: i32, natural: Option<(u32, u32)>) -> Degree {
+        let natural = natural.map(|(size, face)| (BigUint::from(size), BigUint::from(face)));
+        degree(&BigRational::from_integer(margin.into()), natural.as_ref())
+    }
+
+    #[test]
+    fn margins() {
+        use Degree::*;
+
+        for (margin, expected) in [
+            (10, CriticalSuccess),
+            (9, Success),
+            (0, Success),
+            (-1, Failure),
+            (-9, Failure),
+            (-10, CriticalFailure),
+        ] {
+            assert_eq!(pathfinder(margin, None), expected, "margin {margin}");
+        }
+    }
+
+    #[test]
+    fn naturals() {
+        use Degree::*;
+
+        for (margin, face, expected) in [
+            (-10, 20, Failure),
+            (-1, 20, Success),
+            (0, 20, CriticalSuccess),
+            (10, 20, CriticalSuccess),
+            (10, 1, Success),
+            (0, 1, Failure),
+            (-1, 1, CriticalFailure),
+            (-10, 1, CriticalFailure),
+            (0, 10, Success),
+        ] {
+            assert_eq!(
+                pathfinder(margin, Some((20, face))),
+                expected,
+                "margin {margin} with a natural {face}"
+            );
+        }
+    }
+
+    #[test]
+    fn natural_of_other_dice() {
+        assert_eq!(pathfinder(0, Some((6, 1))), Degree::Success);
+        assert_eq!(pathfinder(-1, Some((100, 20))), Degree::Failure);
+    }
+}